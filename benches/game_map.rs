@@ -0,0 +1,133 @@
+use adventure::game::{
+    components::Position,
+    resources::game_map::{GameMap, TileProperties},
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use specs::{Builder, Entity, World, WorldExt};
+use std::collections::{HashMap, HashSet};
+
+const SIZE: i32 = 500;
+
+/// The original HashMap-per-tile layout, kept around to compare against
+#[derive(Default)]
+struct HashMapGameMap {
+    data: HashMap<Position, (TileProperties, HashSet<Entity>)>,
+}
+
+impl HashMapGameMap {
+    fn add(&mut self, coordinate: &Position, entity: Entity) {
+        self.data
+            .entry(coordinate.clone())
+            .or_default()
+            .1
+            .insert(entity);
+    }
+
+    fn mark_tile(&mut self, coordinate: &Position, flags: TileProperties) {
+        self.data.entry(coordinate.clone()).or_default().0 |= flags;
+    }
+
+    fn tile_is(&self, coordinate: &Position, flags: TileProperties) -> bool {
+        match self.data.get(coordinate) {
+            None => false,
+            Some(entry) => entry.0.contains(flags),
+        }
+    }
+
+    fn get_entities(&self, coordinate: &Position) -> Option<&HashSet<Entity>> {
+        self.data.get(coordinate).map(|entry| &entry.1)
+    }
+}
+
+fn build_entities() -> Vec<Entity> {
+    let mut world = World::new();
+
+    (0..SIZE * SIZE)
+        .map(|_| world.create_entity().build())
+        .collect()
+}
+
+fn positions() -> impl Iterator<Item = Position> {
+    (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| Position::new(x, y)))
+}
+
+fn fill_chunked(entities: &[Entity]) -> GameMap {
+    let mut map = GameMap::new();
+
+    for (pos, entity) in positions().zip(entities) {
+        map.add(&pos, *entity);
+
+        if pos.x % 7 == 0 {
+            map.mark_tile(&pos, TileProperties::BLOCKED);
+        }
+    }
+
+    map
+}
+
+fn fill_hash_map(entities: &[Entity]) -> HashMapGameMap {
+    let mut map = HashMapGameMap::default();
+
+    for (pos, entity) in positions().zip(entities) {
+        map.add(&pos, *entity);
+
+        if pos.x % 7 == 0 {
+            map.mark_tile(&pos, TileProperties::BLOCKED);
+        }
+    }
+
+    map
+}
+
+fn fill(c: &mut Criterion) {
+    let entities = build_entities();
+    let mut group = c.benchmark_group("fill 500x500");
+
+    group.sample_size(10);
+    group.bench_function("chunked", |b| b.iter(|| fill_chunked(&entities)));
+    group.bench_function("hash map", |b| b.iter(|| fill_hash_map(&entities)));
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let entities = build_entities();
+    let chunked = fill_chunked(&entities);
+    let hash_map = fill_hash_map(&entities);
+    let mut group = c.benchmark_group("query 500x500");
+
+    group.sample_size(10);
+    group.bench_function("chunked tile_is", |b| {
+        b.iter(|| {
+            positions()
+                .filter(|pos| chunked.tile_is(pos, TileProperties::BLOCKED))
+                .count()
+        })
+    });
+    group.bench_function("hash map tile_is", |b| {
+        b.iter(|| {
+            positions()
+                .filter(|pos| hash_map.tile_is(pos, TileProperties::BLOCKED))
+                .count()
+        })
+    });
+    group.bench_function("chunked get_entities", |b| {
+        b.iter(|| {
+            positions()
+                .filter_map(|pos| chunked.get_entities(&pos))
+                .map(|entities| black_box(entities.len()))
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("hash map get_entities", |b| {
+        b.iter(|| {
+            positions()
+                .filter_map(|pos| hash_map.get_entities(&pos))
+                .map(|entities| black_box(entities.len()))
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, fill, query);
+criterion_main!(benches);
@@ -0,0 +1,172 @@
+use super::material::Material;
+use serde::{Deserialize, Serialize};
+use specs::{Component, Entity, HashMapStorage, ReadStorage};
+
+/// Where on a creature an item goes.  Only one item fits in each slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Slot {
+    Weapon,
+    Armor,
+    Light,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 3] = [Slot::Weapon, Slot::Armor, Slot::Light];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Slot::Weapon => "Weapon",
+            Slot::Armor => "Armor",
+            Slot::Light => "Light",
+        }
+    }
+}
+
+/// An item that can be worn or wielded, and what it adds while it is.  Armor
+/// protects as well as its material is hard, and anything in the light slot
+/// shines with its own light source.
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(HashMapStorage)]
+pub struct Equippable {
+    pub slot: Slot,
+    pub attack: i32,
+    pub vision: i32,
+}
+
+/// What a creature has equipped, one item per slot.  Equipped items stay in
+/// the inventory too.
+#[derive(Clone, Component, Debug, Default, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Equipment {
+    pub weapon: Option<Entity>,
+    pub armor: Option<Entity>,
+    pub light: Option<Entity>,
+}
+
+/// Everything equipped items add to a creature's own stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bonuses {
+    pub attack: i32,
+    pub defense: i32,
+    pub vision: i32,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: Slot) -> Option<Entity> {
+        match slot {
+            Slot::Weapon => self.weapon,
+            Slot::Armor => self.armor,
+            Slot::Light => self.light,
+        }
+    }
+
+    pub fn get_mut(&mut self, slot: Slot) -> &mut Option<Entity> {
+        match slot {
+            Slot::Weapon => &mut self.weapon,
+            Slot::Armor => &mut self.armor,
+            Slot::Light => &mut self.light,
+        }
+    }
+
+    /// Which slot the item is equipped in, if it is at all
+    pub fn slot_of(&self, item: Entity) -> Option<Slot> {
+        Slot::ALL
+            .iter()
+            .copied()
+            .find(|slot| self.get(*slot) == Some(item))
+    }
+
+    pub fn bonuses(
+        &self,
+        equippables: &ReadStorage<Equippable>,
+        materials: &ReadStorage<Material>,
+    ) -> Bonuses {
+        let mut bonuses = Bonuses::default();
+
+        for slot in Slot::ALL.iter() {
+            let item = match self.get(*slot) {
+                Some(item) => item,
+                None => continue,
+            };
+
+            if let Some(equippable) = equippables.get(item) {
+                bonuses.attack += equippable.attack;
+                bonuses.vision += equippable.vision;
+            }
+
+            if *slot == Slot::Armor {
+                bonuses.defense += materials.get(item).map_or(0, |material| material.hardness);
+            }
+        }
+
+        bonuses
+    }
+}
+
+/// Bonuses from whatever the entity has equipped, or none if it can't equip
+/// anything
+pub fn bonuses_of(
+    entity: Entity,
+    equipment: &ReadStorage<Equipment>,
+    equippables: &ReadStorage<Equippable>,
+    materials: &ReadStorage<Material>,
+) -> Bonuses {
+    equipment
+        .get(entity)
+        .map(|equipment| equipment.bonuses(equippables, materials))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::material;
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    #[test]
+    fn adds_up_bonuses_from_every_slot() {
+        let mut world = World::new();
+
+        world.register::<Equippable>();
+        world.register::<Material>();
+
+        let sword = world
+            .create_entity()
+            .with(Equippable {
+                slot: Slot::Weapon,
+                attack: 3,
+                vision: 0,
+            })
+            .with(material::iron())
+            .build();
+        let mail = world
+            .create_entity()
+            .with(Equippable {
+                slot: Slot::Armor,
+                attack: 0,
+                vision: -1,
+            })
+            .with(material::iron())
+            .build();
+
+        let equipment = Equipment {
+            weapon: Some(sword),
+            armor: Some(mail),
+            light: None,
+        };
+
+        let bonuses = equipment.bonuses(&world.read_storage(), &world.read_storage());
+
+        // Only armor protects, however hard the sword is
+        assert_eq!(
+            bonuses,
+            Bonuses {
+                attack: 3,
+                defense: material::iron().hardness,
+                vision: -1,
+            }
+        );
+        assert_eq!(equipment.slot_of(mail), Some(Slot::Armor));
+        assert_eq!(equipment.slot_of(world.create_entity().build()), None);
+    }
+}
@@ -0,0 +1,99 @@
+use super::{material::Material, Sprite, ACTION_COST, DL_FLUID};
+use serde::{Deserialize, Serialize};
+use specs::{Component, VecStorage};
+
+/// Fluids that dissipate evaporate entirely once there's less than this much
+/// volume on a tile
+pub const MIN_VOLUME: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum FluidKind {
+    Smoke,
+    Poison,
+    Water,
+}
+
+impl FluidKind {
+    /// Fraction of the volume on a tile that flows out to its neighbors each
+    /// time unit
+    pub fn spread(&self) -> f32 {
+        match self {
+            FluidKind::Smoke => 0.5,
+            FluidKind::Poison => 0.4,
+            FluidKind::Water => 0.25,
+        }
+    }
+
+    /// Fraction of the volume that disappears each time unit
+    pub fn dissipation(&self) -> f32 {
+        match self {
+            FluidKind::Smoke => 0.05,
+            FluidKind::Poison => 0.03,
+            FluidKind::Water => 0.,
+        }
+    }
+
+    /// Whether the fluid ever goes away on its own.  Those that don't are kept
+    /// however thin they get, so that none of their volume is lost.
+    pub fn evaporates(&self) -> bool {
+        self.dissipation() > 0.
+    }
+
+    /// What a tile holding this much of the fluid is made of, thinner fluids
+    /// being easier to see through
+    pub fn material(&self, volume: f32) -> Material {
+        let (opacity, solid) = match self {
+            FluidKind::Smoke => (0.35, false),
+            FluidKind::Poison => (0.15, false),
+            FluidKind::Water => (0., false),
+        };
+
+        // Rounded so that slowly thinning fluids don't change the map every tick
+        let amount = (volume.min(1.) * 20.).round() / 20.;
+
+        Material {
+            opacity: opacity * amount,
+            visible: true,
+            solid,
+            ..Material::default()
+        }
+    }
+
+    pub fn sprite(&self) -> Sprite {
+        let (rune, fg, bg) = match self {
+            FluidKind::Smoke => ('░', (160, 160, 160), (90, 90, 90)),
+            FluidKind::Poison => ('░', (120, 220, 60), (40, 90, 20)),
+            FluidKind::Water => ('~', (120, 170, 255), (20, 50, 140)),
+        };
+
+        Sprite {
+            fg_r: fg.0,
+            fg_g: fg.1,
+            fg_b: fg.2,
+
+            bg_r: bg.0,
+            bg_g: bg.1,
+            bg_b: bg.2,
+
+            layer: DL_FLUID,
+
+            rune,
+        }
+    }
+
+    /// Extra energy it takes to move into a tile holding this much of the fluid
+    pub fn wading_cost(&self, volume: f32) -> i32 {
+        match self {
+            FluidKind::Water if volume >= 0.5 => ACTION_COST / 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Some amount of a fluid filling a single tile
+#[derive(Clone, Component, Debug, PartialEq, Deserialize, Serialize)]
+#[storage(VecStorage)]
+pub struct Fluid {
+    pub kind: FluidKind,
+    pub volume: f32,
+}
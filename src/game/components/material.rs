@@ -1,33 +1,151 @@
-use specs::{Component, VecStorage};
+use serde::{Deserialize, Serialize};
+use specs::{Component, FlaggedStorage, VecStorage};
 
-#[derive(Component, Default)]
-#[storage(VecStorage)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct Material {
-    pub opaque: bool,
+    /// How much of the light passing through a full block of this stops, from
+    /// 0 for perfectly clear to 1 for something that can't be seen through
+    pub opacity: f32,
+
     pub visible: bool,
     pub solid: bool,
+
+    /// Chance each time unit of catching fire once it's hot enough, where 0
+    /// never burns at all
+    pub flammability: f32,
+    /// How much heat it takes before it can catch fire
+    pub ignition_temperature: f32,
+    /// How many time units it burns for before it's used up
+    pub burn_duration: u32,
+
+    /// How much of every blow armor made of this stops
+    pub hardness: i32,
+}
+
+impl Component for Material {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
 
 pub fn smoke() -> Material {
     Material {
-        opaque: true,
+        opacity: 0.35,
         visible: true,
         solid: false,
+        ..Material::default()
     }
 }
 
 pub fn stone() -> Material {
     Material {
-        opaque: true,
+        opacity: 1.,
         visible: true,
         solid: true,
+        hardness: 2,
+        ..Material::default()
     }
 }
 
 pub fn flesh() -> Material {
     Material {
-        opaque: true,
+        opacity: 1.,
+        visible: true,
+        solid: true,
+        flammability: 0.2,
+        ignition_temperature: 400.,
+        burn_duration: 4,
+        ..Material::default()
+    }
+}
+
+pub fn fog() -> Material {
+    Material {
+        opacity: 0.15,
+        visible: true,
+        solid: false,
+        ..Material::default()
+    }
+}
+
+pub fn foliage() -> Material {
+    Material {
+        opacity: 0.5,
+        visible: true,
+        solid: false,
+        flammability: 0.8,
+        ignition_temperature: 150.,
+        burn_duration: 3,
+        ..Material::default()
+    }
+}
+
+pub fn glass() -> Material {
+    Material {
+        opacity: 0.05,
+        visible: true,
+        solid: true,
+        ..Material::default()
+    }
+}
+
+pub fn wood() -> Material {
+    Material {
+        opacity: 1.,
         visible: true,
         solid: true,
+        flammability: 0.5,
+        ignition_temperature: 200.,
+        burn_duration: 12,
+        hardness: 1,
+    }
+}
+
+/// Tanned hide, tough but flammable
+pub fn leather() -> Material {
+    Material {
+        opacity: 1.,
+        visible: true,
+        solid: true,
+        flammability: 0.3,
+        ignition_temperature: 300.,
+        burn_duration: 6,
+        hardness: 1,
+    }
+}
+
+pub fn iron() -> Material {
+    Material {
+        opacity: 1.,
+        visible: true,
+        solid: true,
+        hardness: 3,
+        ..Material::default()
+    }
+}
+
+/// What's left over once something has burnt away, a thin layer that's easily
+/// walked over and seen past
+pub fn ash() -> Material {
+    Material {
+        opacity: 0.,
+        visible: true,
+        solid: false,
+        ..Material::default()
+    }
+}
+
+/// Looks up one of the predefined materials by name, as used in level files
+pub fn by_name(name: &str) -> Option<Material> {
+    match name {
+        "smoke" => Some(smoke()),
+        "stone" => Some(stone()),
+        "flesh" => Some(flesh()),
+        "fog" => Some(fog()),
+        "foliage" => Some(foliage()),
+        "glass" => Some(glass()),
+        "wood" => Some(wood()),
+        "ash" => Some(ash()),
+        "leather" => Some(leather()),
+        "iron" => Some(iron()),
+        _ => None,
     }
 }
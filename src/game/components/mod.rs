@@ -1,14 +1,21 @@
-use specs::{Component, HashMapStorage, NullStorage, VecStorage};
+use serde::{Deserialize, Serialize};
+use specs::{Component, Entity, FlaggedStorage, HashMapStorage, NullStorage, VecStorage};
 
+pub mod equipment;
+pub mod fluid;
 pub mod material;
 
-#[derive(Clone, Component, Debug, PartialEq, Eq, Hash)]
-#[storage(VecStorage)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
+// Flagged so the game map can keep up with things moving around
+impl Component for Position {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
 impl Position {
     pub fn new(x: i32, y: i32) -> Position {
         Position { x, y }
@@ -423,14 +430,17 @@ pub struct Moved {
     pub to: Position,
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Deserialize, Serialize)]
 pub struct DrawLayer(u8);
 
 pub const DL_FLOOR: DrawLayer = DrawLayer(0);
 pub const DL_WALLS: DrawLayer = DrawLayer(5);
+pub const DL_CORPSES: DrawLayer = DrawLayer(10);
+pub const DL_ITEMS: DrawLayer = DrawLayer(20);
+pub const DL_FLUID: DrawLayer = DrawLayer(50);
 pub const DL_ENTITY: DrawLayer = DrawLayer(100);
 
-#[derive(Clone, Component, Debug, PartialEq, Eq)]
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[storage(VecStorage)]
 pub struct Sprite {
     pub fg_r: u8,
@@ -454,10 +464,248 @@ pub struct Player;
 #[storage(NullStorage)]
 pub struct Visible;
 
+/// Something that gives off light, brightest at its own tile and fading out
+/// to nothing just past the radius
+#[derive(Clone, Component, Debug, PartialEq, Deserialize, Serialize)]
+#[storage(HashMapStorage)]
+pub struct LightSource {
+    pub radius: i32,
+    pub color: (u8, u8, u8),
+
+    /// How bright the light is at the source, where 1 is fully lit
+    pub intensity: f32,
+}
+
+/// Something on fire, giving off light and smoke until it's used up
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(HashMapStorage)]
+pub struct Burning {
+    /// Time units left before there's nothing left to burn
+    pub remaining: u32,
+}
+
+impl Burning {
+    /// The light every fire gives off
+    pub fn light() -> LightSource {
+        LightSource {
+            radius: 5,
+            color: (255, 150, 60),
+            intensity: 1.,
+        }
+    }
+}
+
+/// What an entity can currently see.  Recomputed only when marked dirty, so
+/// anything that changes what's in view should set the flag.
 #[derive(Clone, Component, Debug, PartialEq, Eq)]
 #[storage(VecStorage)]
+pub struct Viewshed {
+    pub radius: i32,
+    pub visible: std::collections::HashSet<Position>,
+    pub dirty: bool,
+}
+
+impl Viewshed {
+    pub fn new(radius: i32) -> Viewshed {
+        Viewshed {
+            radius,
+            visible: std::collections::HashSet::new(),
+            dirty: true,
+        }
+    }
+
+    pub fn can_see(&self, pos: &Position) -> bool {
+        self.visible.contains(pos)
+    }
+}
+
+/// Every tile this entity has seen so far, so the map can still be drawn after
+/// it goes out of view
+#[derive(Clone, Component, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(HashMapStorage)]
+pub struct Explored {
+    pub tiles: std::collections::HashSet<Position>,
+}
+
+/// How a computer controlled creature decides where to go
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Behavior {
+    /// Stays put
+    #[default]
+    Idle,
+
+    /// Stumbles around at random
+    Wander,
+
+    /// Heads for the player while it can see them
+    Chase,
+
+    /// Runs from the player while it can see them
+    Flee,
+}
+
+/// Energy it takes to do anything that uses up a turn
+pub const ACTION_COST: i32 = 100;
+
+/// Energy gained each time unit by something of ordinary speed
+pub const NORMAL_SPEED: i32 = 10;
+
+/// Anything with energy takes turns.  Every time unit it gains energy equal to
+/// its speed, and once it has at least ACTION_COST it can act.
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(VecStorage)]
+pub struct Energy {
+    pub value: i32,
+    pub speed: i32,
+}
+
+impl Energy {
+    pub fn new(speed: i32) -> Energy {
+        Energy { value: 0, speed }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.value >= ACTION_COST
+    }
+
+    pub fn spend(&mut self) {
+        self.value -= ACTION_COST;
+    }
+}
+
+#[derive(Clone, Component, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(HashMapStorage)]
+pub struct Ai {
+    pub behavior: Behavior,
+}
+
+/// What to call something when telling the player about it
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(HashMapStorage)]
+pub struct Name {
+    pub name: String,
+}
+
+/// Which side a creature is on.  Creatures only fight those on another side.
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(VecStorage)]
+pub enum Faction {
+    Player,
+    Monsters,
+}
+
+/// How much more punishment a creature can take.  It dies once this reaches 0.
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(VecStorage)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Health {
+        Health { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
+}
+
+/// Stays behind as a corpse on dying, rather than vanishing
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub struct LeavesCorpse;
+
+/// How hard a creature hits when it bumps into an enemy
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(VecStorage)]
+pub struct Attack {
+    pub power: i32,
+}
+
+/// How much of every hit a creature shrugs off
+#[derive(Clone, Component, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[storage(VecStorage)]
+pub struct Defense {
+    pub armor: i32,
+}
+
+/// What happens when an item gets used
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ItemEffect {
+    /// Restores up to this much health, using the item up
+    Heal(i32),
+}
+
+/// Something small enough to be picked up and carried around.  Items lying on
+/// the floor have a position like anything else, carried ones don't.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Item {
+    /// None for things that are only good for carrying around
+    pub effect: Option<ItemEffect>,
+}
+
+impl Component for Item {
+    type Storage = FlaggedStorage<Self, HashMapStorage<Self>>;
+}
+
+/// Items being carried, in the order they were picked up
+#[derive(Clone, Component, Debug, Default, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Inventory {
+    pub items: Vec<Entity>,
+    pub capacity: usize,
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Inventory {
+        Inventory {
+            items: Vec::new(),
+            capacity,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+}
+
+/// An item taken off the map and into someone's inventory
+#[derive(Clone, Component, Debug, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Carried {
+    pub by: Entity,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Shape {
     Floor,
+    /// Small enough to share a tile with anything that isn't a wall
+    Small,
+    /// Takes up its whole tile, so only one solid one fits
     Medium,
     FullBlock,
 }
+
+impl Component for Shape {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
+impl Shape {
+    /// How much of its tile this shape fills, as far as blocking sight goes
+    pub fn obstruction(&self) -> f32 {
+        match self {
+            Shape::Floor => 0.,
+            Shape::Small => 0.05,
+            Shape::Medium => 0.25,
+            Shape::FullBlock => 1.,
+        }
+    }
+
+    /// Whether something solid of this shape keeps others of the same shape
+    /// out of its tile
+    pub fn occupies_tile(&self) -> bool {
+        *self == Shape::Medium
+    }
+}
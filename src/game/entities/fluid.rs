@@ -0,0 +1,22 @@
+use super::super::components::{
+    self,
+    fluid::{Fluid, FluidKind},
+};
+use specs::{Builder, World, WorldExt};
+
+/// Pours some fluid onto a tile, where it will spread out over time
+pub fn create_in(
+    world: &mut World,
+    pos: components::Position,
+    kind: FluidKind,
+    volume: f32,
+) -> specs::Entity {
+    world
+        .create_entity()
+        .with(pos)
+        .with(kind.sprite())
+        .with(kind.material(volume))
+        .with(components::Shape::FullBlock)
+        .with(Fluid { kind, volume })
+        .build()
+}
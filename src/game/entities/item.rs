@@ -0,0 +1,133 @@
+use super::super::{
+    components::{self, equipment},
+    level::Level,
+    resources,
+};
+use rand::seq::SliceRandom;
+use specs::{Builder, World, WorldExt};
+
+/// How much health a healing potion gives back
+pub const POTION_HEALING: i32 = 10;
+
+/// Every kind of item that can turn up lying around
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    HealingPotion,
+    Dagger,
+    Sword,
+    LeatherArmor,
+    ChainMail,
+    Lantern,
+}
+
+pub fn create_in(world: &mut World, pos: components::Position, kind: Kind) -> specs::Entity {
+    let (name, rune, (fg_r, fg_g, fg_b)) = match kind {
+        Kind::HealingPotion => ("healing potion", '!', (255, 60, 90)),
+        Kind::Dagger => ("dagger", '/', (200, 200, 210)),
+        Kind::Sword => ("sword", '/', (150, 190, 255)),
+        Kind::LeatherArmor => ("leather armor", '[', (170, 110, 60)),
+        Kind::ChainMail => ("chain mail", '[', (180, 180, 190)),
+        Kind::Lantern => ("lantern", '*', (255, 220, 120)),
+    };
+
+    let material = match kind {
+        Kind::HealingPotion => components::material::glass(),
+        Kind::LeatherArmor => components::material::leather(),
+        _ => components::material::iron(),
+    };
+
+    let equippable = |slot, attack, vision| equipment::Equippable {
+        slot,
+        attack,
+        vision,
+    };
+
+    let builder = world
+        .create_entity()
+        .with(pos)
+        .with(components::Sprite {
+            fg_r,
+            fg_g,
+            fg_b,
+            bg_r: 0,
+            bg_g: 0,
+            bg_b: 0,
+            layer: components::DL_ITEMS,
+            rune,
+        })
+        .with(components::Name {
+            name: String::from(name),
+        })
+        .with(material)
+        .with(components::Shape::Small);
+
+    match kind {
+        Kind::HealingPotion => builder.with(components::Item {
+            effect: Some(components::ItemEffect::Heal(POTION_HEALING)),
+        }),
+        Kind::Dagger => builder
+            .with(components::Item { effect: None })
+            .with(equippable(equipment::Slot::Weapon, 2, 0)),
+        Kind::Sword => builder
+            .with(components::Item { effect: None })
+            .with(equippable(equipment::Slot::Weapon, 4, 0)),
+        Kind::LeatherArmor => builder
+            .with(components::Item { effect: None })
+            .with(equippable(equipment::Slot::Armor, 0, 0)),
+
+        // Heavy enough to get in the way of looking around
+        Kind::ChainMail => builder
+            .with(components::Item { effect: None })
+            .with(equippable(equipment::Slot::Armor, 0, -2)),
+        Kind::Lantern => builder
+            .with(components::Item { effect: None })
+            .with(equippable(equipment::Slot::Light, 0, 4))
+            .with(components::LightSource {
+                radius: 14,
+                color: (255, 210, 140),
+                intensity: 1.5,
+            }),
+    }
+    .build()
+}
+
+/// Scatters items over the floors of the level, using the game's random number
+/// generator.  Potions turn up more than anything else.
+pub fn populate(world: &mut World, level: &Level, count: usize) -> Vec<specs::Entity> {
+    let kinds = [
+        Kind::HealingPotion,
+        Kind::HealingPotion,
+        Kind::HealingPotion,
+        Kind::Dagger,
+        Kind::Sword,
+        Kind::LeatherArmor,
+        Kind::ChainMail,
+        Kind::Lantern,
+    ];
+
+    let floors: Vec<&components::Position> = level
+        .tiles
+        .iter()
+        .filter(|(_, tile)| tile.shape == components::Shape::Floor)
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let picks: Vec<(components::Position, Kind)> = {
+        let mut rng = world.write_resource::<resources::GameRng>();
+
+        let positions: Vec<components::Position> = floors
+            .choose_multiple(&mut rng.0, count)
+            .map(|pos| (*pos).clone())
+            .collect();
+
+        positions
+            .into_iter()
+            .map(|pos| (pos, *kinds.choose(&mut rng.0).unwrap()))
+            .collect()
+    };
+
+    picks
+        .into_iter()
+        .map(|(pos, kind)| create_in(world, pos, kind))
+        .collect()
+}
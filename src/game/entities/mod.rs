@@ -1,2 +1,5 @@
+pub mod fluid;
+pub mod item;
 pub mod map;
+pub mod monster;
 pub mod player;
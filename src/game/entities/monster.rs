@@ -0,0 +1,107 @@
+use super::super::{components, level::Level, resources};
+use rand::seq::SliceRandom;
+use specs::{Builder, World, WorldExt};
+
+/// Monsters won't be placed closer to the spawn than this, so the player gets
+/// a moment to look around
+const MIN_SPAWN_DISTANCE: i32 = 8;
+
+/// Monsters are a bit shortsighted compared to the player
+pub const VISION_RADIUS: i32 = 12;
+
+pub fn create_in(
+    world: &mut World,
+    pos: components::Position,
+    behavior: components::Behavior,
+) -> specs::Entity {
+    let (name, rune, fg_r, fg_g, fg_b, speed) = match behavior {
+        components::Behavior::Idle => ("slug", 's', 160, 160, 160, components::NORMAL_SPEED),
+        components::Behavior::Wander => {
+            ("rat", 'r', 200, 150, 90, components::NORMAL_SPEED * 12 / 10)
+        }
+        components::Behavior::Chase => (
+            "goblin",
+            'g',
+            64,
+            220,
+            64,
+            components::NORMAL_SPEED * 8 / 10,
+        ),
+        components::Behavior::Flee => ("kobold", 'k', 220, 220, 64, components::NORMAL_SPEED),
+    };
+
+    // Goblins are the only ones really looking for a fight
+    let (health, power) = match behavior {
+        components::Behavior::Chase => (10, 4),
+        _ => (6, 2),
+    };
+
+    world
+        .create_entity()
+        .with(pos)
+        .with(components::Sprite {
+            fg_r,
+            fg_g,
+            fg_b,
+            bg_r: 0,
+            bg_g: 0,
+            bg_b: 0,
+            layer: components::DL_ENTITY,
+            rune,
+        })
+        .with(components::Name {
+            name: String::from(name),
+        })
+        .with(components::Ai { behavior })
+        .with(components::Energy::new(speed))
+        .with(components::Viewshed::new(VISION_RADIUS))
+        .with(components::Faction::Monsters)
+        .with(components::Health::new(health))
+        .with(components::Attack { power })
+        .with(components::Defense { armor: 0 })
+        .with(components::material::flesh())
+        .with(components::Shape::Medium)
+        .with(components::LeavesCorpse)
+        .build()
+}
+
+/// Scatters monsters with random behaviors over the floors of the level,
+/// using the game's random number generator
+pub fn populate(world: &mut World, level: &Level, count: usize) -> Vec<specs::Entity> {
+    let behaviors = [
+        components::Behavior::Wander,
+        components::Behavior::Chase,
+        components::Behavior::Chase,
+        components::Behavior::Flee,
+    ];
+
+    let floors: Vec<&components::Position> = level
+        .tiles
+        .iter()
+        .filter(|(pos, tile)| {
+            tile.shape == components::Shape::Floor
+                && pos.distance_squared(&level.spawn)
+                    >= (MIN_SPAWN_DISTANCE * MIN_SPAWN_DISTANCE) as f32
+        })
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let picks: Vec<(components::Position, components::Behavior)> = {
+        let mut rng = world.write_resource::<resources::GameRng>();
+
+        let positions: Vec<components::Position> = floors
+            .choose_multiple(&mut rng.0, count)
+            .map(|pos| (*pos).clone())
+            .collect();
+
+        positions
+            .into_iter()
+            .map(|pos| (pos, *behaviors.choose(&mut rng.0).unwrap()))
+            .collect()
+    };
+
+    picks
+        .into_iter()
+        .map(|(pos, behavior)| create_in(world, pos, behavior))
+        .collect()
+}
@@ -1,6 +1,12 @@
 use super::super::{components, resources};
 use specs::{Builder, World, WorldExt};
 
+/// How many tiles away the player can see
+pub const VISION_RADIUS: i32 = 20;
+
+/// How many items the player can carry at once
+pub const INVENTORY_CAPACITY: usize = 26;
+
 pub fn create_in(world: &mut World, pos: components::Position) -> specs::Entity {
     let ent = world
         .create_entity()
@@ -16,8 +22,26 @@ pub fn create_in(world: &mut World, pos: components::Position) -> specs::Entity
             rune: '@',
         })
         .with(components::Player)
+        .with(components::Viewshed::new(VISION_RADIUS))
+        .with(components::Explored::default())
+        .with(components::LightSource {
+            radius: 10,
+            color: (255, 220, 170),
+            intensity: 1.5,
+        })
+        .with(components::Energy {
+            value: components::ACTION_COST,
+            speed: components::NORMAL_SPEED,
+        })
+        .with(components::Faction::Player)
+        .with(components::Health::new(30))
+        .with(components::Attack { power: 5 })
+        .with(components::Defense { armor: 1 })
+        .with(components::Inventory::new(INVENTORY_CAPACITY))
+        .with(components::equipment::Equipment::default())
         .with(components::material::flesh())
         .with(components::Shape::Medium)
+        .with(components::LeavesCorpse)
         .build();
 
     world.insert(resources::Player { ent });
@@ -0,0 +1,353 @@
+use super::components::Position;
+use super::resources::game_map::{GameMap, TileProperties};
+use std::collections::HashSet;
+
+/// Anything seen through less than this much of the light is lost in the haze
+pub const MIN_TRANSMITTANCE: f32 = 0.2;
+
+/// A slope kept as a fraction so that tiles on the exact edge of a shadow are
+/// always decided the same way, no matter which direction they're seen from
+#[derive(Clone, Copy, Debug)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+/// One row of tiles at a fixed distance from the origin, between two slopes
+#[derive(Clone, Copy, Debug)]
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// Lowest column in the row, rounding ties up
+    fn min_col(&self) -> i32 {
+        (2 * self.depth * self.start.num + self.start.den).div_euclid(2 * self.start.den)
+    }
+
+    /// Highest column in the row, rounding ties down
+    fn max_col(&self) -> i32 {
+        -(self.end.den - 2 * self.depth * self.end.num).div_euclid(2 * self.end.den)
+    }
+
+    /// Whether the center of the tile lies inside the row's slopes.  Only
+    /// those tiles count as seen so that seeing is always mutual.
+    fn is_symmetric(&self, col: i32) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+/// The slope to the edge of the tile nearest the start of its row
+fn slope(depth: i32, col: i32) -> Slope {
+    Slope {
+        num: 2 * col - 1,
+        den: 2 * depth,
+    }
+}
+
+/// Turns a depth and column within one of the four quadrants back into a
+/// position on the map
+fn transform(origin: &Position, quadrant: usize, depth: i32, col: i32) -> Position {
+    match quadrant {
+        0 => Position::new(origin.x + col, origin.y - depth),
+        1 => Position::new(origin.x + depth, origin.y + col),
+        2 => Position::new(origin.x + col, origin.y + depth),
+        _ => Position::new(origin.x - depth, origin.y + col),
+    }
+}
+
+/// Everything visible from the origin within the radius, using symmetric
+/// shadowcasting: if one tile can see another, the other can always see it
+/// back.  Opaque tiles that face the origin are visible themselves.
+pub fn compute<F: Fn(&Position) -> bool>(
+    origin: &Position,
+    radius: i32,
+    is_opaque: F,
+) -> HashSet<Position> {
+    let mut visible = HashSet::new();
+    let in_range = |depth: i32, col: i32| depth * depth + col * col <= radius * radius + radius;
+
+    visible.insert(origin.clone());
+
+    for quadrant in 0..4 {
+        let mut rows = vec![Row {
+            depth: 1,
+            start: Slope { num: -1, den: 1 },
+            end: Slope { num: 1, den: 1 },
+        }];
+
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+
+            // Whether the previous tile in the row was opaque, if there was one
+            let mut previous: Option<bool> = None;
+
+            for col in row.min_col()..=row.max_col() {
+                let pos = transform(origin, quadrant, row.depth, col);
+                let opaque = is_opaque(&pos);
+
+                if (opaque || row.is_symmetric(col)) && in_range(row.depth, col) {
+                    visible.insert(pos);
+                }
+
+                if previous == Some(true) && !opaque {
+                    row.start = slope(row.depth, col);
+                }
+
+                if previous == Some(false) && opaque {
+                    let mut next = row.next();
+
+                    next.end = slope(row.depth, col);
+                    rows.push(next);
+                }
+
+                previous = Some(opaque);
+            }
+
+            if previous == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+
+    visible
+}
+
+/// Tiles strictly between two positions along a straight line.  The line is
+/// always drawn from the same end so that it's the same in both directions.
+fn between(a: &Position, b: &Position) -> Vec<Position> {
+    let (from, to) = if (a.x, a.y) <= (b.x, b.y) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let step_x = if from.x < to.x { 1 } else { -1 };
+    let step_y = if from.y < to.y { 1 } else { -1 };
+
+    let mut tiles = Vec::new();
+    let mut x = from.x;
+    let mut y = from.y;
+    let mut error = dx + dy;
+
+    loop {
+        let doubled = 2 * error;
+
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+
+        if x == to.x && y == to.y {
+            return tiles;
+        }
+
+        tiles.push(Position::new(x, y));
+    }
+}
+
+/// How much light makes it from one tile to another through whatever is only
+/// partly see through in between, where 1 means nothing is in the way.  Opaque
+/// tiles are skipped, since shadowcasting already decides what they hide and a
+/// line that only grazes one shouldn't hide anything.
+pub fn transmittance(map: &GameMap, from: &Position, to: &Position) -> f32 {
+    if from == to {
+        return 1.;
+    }
+
+    between(from, to)
+        .iter()
+        .filter(|pos| !map.tile_is(pos, TileProperties::OPAQUE))
+        .fold(1., |light, pos| light * (1. - map.opacity(pos)))
+}
+
+/// Everything visible from the origin on the map.  Opaque tiles cast shadows,
+/// while anything partly see through dims the view cumulatively along the way.
+pub fn field_of_view(map: &GameMap, origin: &Position, radius: i32) -> HashSet<Position> {
+    compute(origin, radius, |pos| {
+        map.tile_is(pos, TileProperties::OPAQUE)
+    })
+    .into_iter()
+    .filter(|pos| transmittance(map, origin, pos) >= MIN_TRANSMITTANCE)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pathfinding::test_map;
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    #[test]
+    fn sees_whole_open_room() {
+        let map = test_map::build(&[
+            ".....", //
+            ".....", //
+            ".....", //
+        ]);
+
+        let visible = field_of_view(&map, &Position::new(2, 1), 10);
+
+        for x in 0..5 {
+            for y in 0..3 {
+                assert!(visible.contains(&Position::new(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn walls_block_what_is_behind_them() {
+        let map = test_map::build(&[
+            ".....", //
+            "..#..", //
+            ".....", //
+        ]);
+
+        let visible = field_of_view(&map, &Position::new(0, 1), 10);
+
+        assert!(visible.contains(&Position::new(2, 1)));
+        assert!(!visible.contains(&Position::new(3, 1)));
+        assert!(!visible.contains(&Position::new(4, 1)));
+    }
+
+    #[test]
+    fn sees_every_wall_next_to_origin() {
+        let map = test_map::build(&[
+            ".#....", //
+            ".#....", //
+            ".#....", //
+        ]);
+
+        let visible = field_of_view(&map, &Position::new(0, 1), 10);
+
+        for y in 0..3 {
+            assert!(visible.contains(&Position::new(1, y)));
+
+            for x in 2..6 {
+                assert!(!visible.contains(&Position::new(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn sees_past_wall_corners() {
+        let map = test_map::build(&[
+            "...", //
+            ".#.", //
+            "...", //
+        ]);
+
+        let visible = field_of_view(&map, &Position::new(0, 0), 10);
+
+        // The line to it grazes the wall, but shadowcasting already decided
+        // the tile is in view
+        assert!(visible.contains(&Position::new(2, 1)));
+        assert!(visible.contains(&Position::new(1, 2)));
+    }
+
+    #[test]
+    fn haze_builds_up_along_the_way() {
+        let mut map = test_map::build(&["............"]);
+        let mut world = World::new();
+
+        for x in 1..8 {
+            let haze = world.create_entity().build();
+
+            map.place_with_opacity(&Position::new(x, 0), haze, TileProperties::empty(), 0.3);
+        }
+
+        let origin = Position::new(0, 0);
+        let visible = field_of_view(&map, &origin, 20);
+
+        assert!(transmittance(&map, &origin, &Position::new(2, 0)) > 0.6);
+        assert!(visible.contains(&Position::new(4, 0)));
+        assert!(!visible.contains(&Position::new(7, 0)));
+        assert!(!visible.contains(&Position::new(10, 0)));
+    }
+
+    #[test]
+    fn lines_are_the_same_both_ways() {
+        let a = Position::new(-3, 2);
+        let b = Position::new(5, -4);
+
+        let mut there = between(&a, &b);
+        let mut back = between(&b, &a);
+
+        there.sort_by_key(|pos| (pos.x, pos.y));
+        back.sort_by_key(|pos| (pos.x, pos.y));
+
+        assert_eq!(there, back);
+        assert!(!there.contains(&a));
+        assert!(!there.contains(&b));
+    }
+
+    #[test]
+    fn stops_at_radius() {
+        let map = test_map::build(&["..........."]);
+
+        let visible = field_of_view(&map, &Position::new(0, 0), 4);
+
+        assert!(visible.contains(&Position::new(4, 0)));
+        assert!(!visible.contains(&Position::new(5, 0)));
+    }
+
+    #[test]
+    fn vision_is_symmetric() {
+        let map = test_map::build(&[
+            "..........", //
+            ".#..#.....", //
+            "...#...#..", //
+            "......#...", //
+            ".#.....#..", //
+            "...#......", //
+        ]);
+
+        let mut floors = Vec::new();
+
+        for y in 0..6 {
+            for x in 0..10 {
+                let pos = Position::new(x, y);
+
+                if !map.tile_is(&pos, TileProperties::OPAQUE) {
+                    floors.push(pos);
+                }
+            }
+        }
+
+        let seen: Vec<HashSet<Position>> = floors
+            .iter()
+            .map(|from| field_of_view(&map, from, 20))
+            .collect();
+
+        for (i, from) in floors.iter().enumerate() {
+            for (j, to) in floors.iter().enumerate() {
+                assert_eq!(
+                    seen[i].contains(to),
+                    seen[j].contains(from),
+                    "{:?} and {:?} don't agree",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+}
@@ -9,6 +9,24 @@ pub enum Action {
     Down,
     Left,
     Right,
+
+    /// Look back through older messages in the log, or forward again
+    ScrollLogUp,
+    ScrollLogDown,
+
+    /// Pick up whatever is lying underfoot
+    PickUp,
+
+    /// Open or close the inventory
+    Inventory,
+
+    /// Drop or use the item picked out in the inventory
+    Drop,
+    Use,
+
+    /// Put on or take off the item picked out in the inventory
+    Equip,
+    Unequip,
 }
 
 pub fn to_action(c: Key) -> Option<Action> {
@@ -19,6 +37,16 @@ pub fn to_action(c: Key) -> Option<Action> {
         Key::Down | Key::Char('j') => Some(Action::Down),
         Key::Right | Key::Char('l') => Some(Action::Right),
         Key::Left | Key::Char('h') => Some(Action::Left),
+
+        Key::PageUp | Key::Char('[') => Some(Action::ScrollLogUp),
+        Key::PageDown | Key::Char(']') => Some(Action::ScrollLogDown),
+
+        Key::Char('g') | Key::Char(',') => Some(Action::PickUp),
+        Key::Char('i') => Some(Action::Inventory),
+        Key::Char('d') => Some(Action::Drop),
+        Key::Char('u') | Key::Char('\n') => Some(Action::Use),
+        Key::Char('e') => Some(Action::Equip),
+        Key::Char('r') => Some(Action::Unequip),
         _ => None,
     }
 }
@@ -106,6 +134,38 @@ mod tests {
         assert_eq!(actions[0], Action::Down);
     }
 
+    #[test]
+    fn reads_log_scrolling_keys() {
+        let keys = b"[]".keys();
+        let mut input = Input::new(keys);
+
+        let actions = input.step();
+
+        assert_eq!(actions, vec![Action::ScrollLogUp, Action::ScrollLogDown]);
+    }
+
+    #[test]
+    fn reads_item_keys() {
+        let keys = b"g,idu\ner".keys();
+        let mut input = Input::new(keys);
+
+        let actions = input.step();
+
+        assert_eq!(
+            actions,
+            vec![
+                Action::PickUp,
+                Action::PickUp,
+                Action::Inventory,
+                Action::Drop,
+                Action::Use,
+                Action::Use,
+                Action::Equip,
+                Action::Unequip,
+            ]
+        );
+    }
+
     #[test]
     fn reads_multiple_different_keys() {
         let keys = b"kj".keys();
@@ -0,0 +1,224 @@
+use super::super::components::Position;
+use super::{build_level, Level};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use std::collections::HashSet;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub width: i32,
+    pub height: i32,
+
+    /// Percent chance that a tile starts out as wall before smoothing
+    pub fill_percent: u32,
+
+    pub smoothing_steps: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: 80,
+            height: 40,
+            fill_percent: 45,
+            smoothing_steps: 5,
+        }
+    }
+}
+
+struct Grid {
+    width: i32,
+    height: i32,
+    walls: Vec<bool>,
+}
+
+impl Grid {
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return true;
+        }
+
+        self.walls[(y * self.width + x) as usize]
+    }
+
+    fn is_edge(&self, x: i32, y: i32) -> bool {
+        x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1
+    }
+
+    fn wall_neighbors(&self, x: i32, y: i32) -> usize {
+        let mut count = 0;
+
+        for offset_x in -1..=1 {
+            for offset_y in -1..=1 {
+                if (offset_x != 0 || offset_y != 0) && self.is_wall(x + offset_x, y + offset_y) {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    fn smooth(&self) -> Grid {
+        let mut walls = Vec::with_capacity(self.walls.len());
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let neighbors = self.wall_neighbors(x, y);
+
+                walls.push(if self.is_edge(x, y) || neighbors > 4 {
+                    true
+                } else if neighbors < 4 {
+                    false
+                } else {
+                    self.is_wall(x, y)
+                });
+            }
+        }
+
+        Grid {
+            width: self.width,
+            height: self.height,
+            walls,
+        }
+    }
+
+    fn floor_closest_to(&self, target: &Position) -> Option<Position> {
+        let mut closest = None;
+        let mut closest_distance = f32::MAX;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = Position::new(x, y);
+                let distance = pos.distance_squared(target);
+
+                if !self.is_wall(x, y) && distance < closest_distance {
+                    closest_distance = distance;
+                    closest = Some(pos);
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Every floor reachable from the start, moving in the four cardinal directions
+    fn flood_fill(&self, start: &Position) -> HashSet<Position> {
+        let mut reached = HashSet::new();
+        let mut open = vec![start.clone()];
+
+        while let Some(pos) = open.pop() {
+            if self.is_wall(pos.x, pos.y) || !reached.insert(pos.clone()) {
+                continue;
+            }
+
+            open.push(pos.up());
+            open.push(pos.down());
+            open.push(pos.left());
+            open.push(pos.right());
+        }
+
+        reached
+    }
+}
+
+/// Generates an organic cave by randomly filling the map and smoothing it out
+/// with cellular automata.  Pockets that can't be reached from the spawn are
+/// filled back in, so every floor in the result is reachable.  The same seed
+/// and settings always produce the same level.
+pub fn generate(seed: u64, settings: &Settings) -> Level {
+    let mut rng = Pcg32::seed_from_u64(seed);
+
+    let width = settings.width.max(3);
+    let height = settings.height.max(3);
+
+    let mut grid = Grid {
+        width,
+        height,
+        walls: Vec::with_capacity((width * height) as usize),
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let is_wall = grid.is_edge(x, y) || rng.gen_range(0, 100) < settings.fill_percent;
+
+            grid.walls.push(is_wall);
+        }
+    }
+
+    for _ in 0..settings.smoothing_steps {
+        grid = grid.smooth();
+    }
+
+    let center = Position::new(width / 2, height / 2);
+
+    let spawn = match grid.floor_closest_to(&center) {
+        Some(pos) => pos,
+        None => {
+            // Everything filled in, so hollow out a spot to stand at least
+            grid.walls[(center.y * width + center.x) as usize] = false;
+            center
+        }
+    };
+
+    let floors = grid.flood_fill(&spawn);
+
+    build_level(&floors, spawn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_level::{floors_of, reachable_from_spawn};
+    use super::*;
+
+    #[test]
+    fn same_seed_generates_same_level() {
+        let settings = Settings::default();
+
+        assert_eq!(generate(1234, &settings), generate(1234, &settings));
+    }
+
+    #[test]
+    fn different_seeds_generate_different_levels() {
+        let settings = Settings::default();
+
+        assert_ne!(generate(1234, &settings), generate(4321, &settings));
+    }
+
+    #[test]
+    fn all_floors_reachable_from_spawn() {
+        for seed in 0..20 {
+            let level = generate(seed, &Settings::default());
+            let floors = floors_of(&level);
+
+            assert!(floors.contains(&level.spawn));
+            assert_eq!(
+                reachable_from_spawn(&level).len(),
+                floors.len(),
+                "Seed {} has unreachable floors",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn generates_a_reasonable_amount_of_cave() {
+        let settings = Settings::default();
+        let level = generate(42, &settings);
+
+        // Smoothing shouldn't collapse the whole thing into a tiny pocket
+        assert!(floors_of(&level).len() as i32 > settings.width * settings.height / 5);
+    }
+
+    #[test]
+    fn still_has_a_spawn_when_completely_filled() {
+        let settings = Settings {
+            fill_percent: 100,
+            ..Settings::default()
+        };
+        let level = generate(1, &settings);
+
+        assert_eq!(floors_of(&level).len(), 1);
+        assert!(floors_of(&level).contains(&level.spawn));
+    }
+}
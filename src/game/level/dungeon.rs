@@ -0,0 +1,184 @@
+use super::super::components::Position;
+use super::{build_level, Level};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use std::collections::HashSet;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub width: i32,
+    pub height: i32,
+    pub max_rooms: usize,
+    pub min_room_size: i32,
+    pub max_room_size: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: 80,
+            height: 40,
+            max_rooms: 20,
+            min_room_size: 4,
+            max_room_size: 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Room {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Room {
+    fn center(&self) -> Position {
+        Position::new(self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    // Leaves at least one tile between rooms so walls never get shared
+    fn intersects(&self, other: &Room) -> bool {
+        self.x <= other.x + other.width
+            && self.x + self.width >= other.x
+            && self.y <= other.y + other.height
+            && self.y + self.height >= other.y
+    }
+}
+
+fn carve_room(floors: &mut HashSet<Position>, room: &Room) {
+    for x in room.x..room.x + room.width {
+        for y in room.y..room.y + room.height {
+            floors.insert(Position::new(x, y));
+        }
+    }
+}
+
+fn carve_horizontal(floors: &mut HashSet<Position>, x1: i32, x2: i32, y: i32) {
+    for x in x1.min(x2)..=x1.max(x2) {
+        floors.insert(Position::new(x, y));
+    }
+}
+
+fn carve_vertical(floors: &mut HashSet<Position>, y1: i32, y2: i32, x: i32) {
+    for y in y1.min(y2)..=y1.max(y2) {
+        floors.insert(Position::new(x, y));
+    }
+}
+
+/// Generates rooms connected by corridors.  Every room is joined to the room
+/// placed before it, so everything is reachable from the spawn in the first room.
+/// The same seed and settings always produce the same level.
+pub fn generate(seed: u64, settings: &Settings) -> Level {
+    let mut rng = Pcg32::seed_from_u64(seed);
+    let mut rooms: Vec<Room> = Vec::new();
+    let mut floors = HashSet::new();
+
+    // Keep rooms inside the map with room for their walls
+    let max_width = settings.max_room_size.min(settings.width - 2);
+    let max_height = settings.max_room_size.min(settings.height - 2);
+    let min_size = settings.min_room_size.max(1);
+
+    for _ in 0..settings.max_rooms.max(1) {
+        let width = rng.gen_range(min_size.min(max_width), max_width + 1);
+        let height = rng.gen_range(min_size.min(max_height), max_height + 1);
+
+        let room = Room {
+            x: rng.gen_range(1, settings.width - width),
+            y: rng.gen_range(1, settings.height - height),
+            width,
+            height,
+        };
+
+        if rooms.iter().any(|other| room.intersects(other)) {
+            continue;
+        }
+
+        carve_room(&mut floors, &room);
+
+        if let Some(previous) = rooms.last() {
+            let from = previous.center();
+            let to = room.center();
+
+            if rng.gen() {
+                carve_horizontal(&mut floors, from.x, to.x, from.y);
+                carve_vertical(&mut floors, from.y, to.y, to.x);
+            } else {
+                carve_vertical(&mut floors, from.y, to.y, from.x);
+                carve_horizontal(&mut floors, from.x, to.x, to.y);
+            }
+        }
+
+        rooms.push(room);
+    }
+
+    build_level(&floors, rooms[0].center())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_level::{floors_of, reachable_from_spawn};
+    use super::*;
+
+    #[test]
+    fn same_seed_generates_same_level() {
+        let settings = Settings::default();
+
+        assert_eq!(generate(1234, &settings), generate(1234, &settings));
+    }
+
+    #[test]
+    fn different_seeds_generate_different_levels() {
+        let settings = Settings::default();
+
+        assert_ne!(generate(1234, &settings), generate(4321, &settings));
+    }
+
+    #[test]
+    fn all_floors_reachable_from_spawn() {
+        for seed in 0..20 {
+            let level = generate(seed, &Settings::default());
+            let floors = floors_of(&level);
+
+            assert!(floors.contains(&level.spawn));
+            assert_eq!(
+                reachable_from_spawn(&level).len(),
+                floors.len(),
+                "Seed {} has unreachable floors",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn floors_are_enclosed_by_walls() {
+        let level = generate(99, &Settings::default());
+        let positions: HashSet<Position> = level.tiles.iter().map(|(pos, _)| pos.clone()).collect();
+
+        for floor in floors_of(&level) {
+            for neighbor in &[floor.up(), floor.down(), floor.left(), floor.right()] {
+                assert!(
+                    positions.contains(neighbor),
+                    "Floor at {:?} leads to the void",
+                    floor
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stays_inside_requested_size() {
+        let settings = Settings {
+            width: 30,
+            height: 20,
+            ..Settings::default()
+        };
+        let level = generate(7, &settings);
+
+        for (pos, _) in &level.tiles {
+            assert!(pos.x >= 0 && pos.x < settings.width);
+            assert!(pos.y >= 0 && pos.y < settings.height);
+        }
+    }
+}
@@ -0,0 +1,585 @@
+use super::super::components::{self, material, Position, Shape, Sprite};
+use super::{Level, Tile};
+use std::collections::HashMap;
+use std::fmt;
+
+/*
+Level files are plain text with two sections, a legend and the map itself:
+
+    # Comments are allowed outside of the map section
+    [legend]
+    X = stone full_block 'X' 255,255,255 128,128,128
+    . = stone floor ' ' 255,255,255 28,28,28
+    @ = spawn .
+
+    [map]
+    XXXXX
+    X.@.X
+    XXXXX
+
+Each legend entry is a glyph followed by material, shape, rune, foreground
+color and background color.  The special `spawn` entry marks where the player
+starts and names the glyph of the tile underneath them.  Spaces in the map
+are empty and produce no entities.  The top left of the map is (0, 0).
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnknownSection(String),
+    MissingMap,
+    InvalidLegendEntry(String),
+    DuplicateGlyph(char),
+    UnknownMaterial(String),
+    UnknownShape(String),
+    InvalidRune(String),
+    InvalidColor(String),
+    UnknownGlyph(char),
+    MissingSpawn,
+    MultipleSpawns,
+}
+
+/// A problem found while parsing a level file, with 1-based line and column
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownSection(name) => write!(f, "unknown section [{}]", name),
+            ErrorKind::MissingMap => write!(f, "no [map] section found"),
+            ErrorKind::InvalidLegendEntry(reason) => write!(f, "invalid legend entry: {}", reason),
+            ErrorKind::DuplicateGlyph(glyph) => write!(f, "glyph '{}' defined twice", glyph),
+            ErrorKind::UnknownMaterial(name) => write!(f, "unknown material '{}'", name),
+            ErrorKind::UnknownShape(name) => write!(f, "unknown shape '{}'", name),
+            ErrorKind::InvalidRune(token) => write!(f, "invalid rune {}, expected 'c'", token),
+            ErrorKind::InvalidColor(token) => {
+                write!(f, "invalid color '{}', expected r,g,b", token)
+            }
+            ErrorKind::UnknownGlyph(glyph) => write!(f, "glyph '{}' is not in the legend", glyph),
+            ErrorKind::MissingSpawn => write!(f, "map has no spawn point"),
+            ErrorKind::MultipleSpawns => write!(f, "map has more than one spawn point"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl std::error::Error for Error {}
+
+enum LegendEntry {
+    Tile(Tile),
+    /// The glyph of the tile underneath, and the column it was given at
+    Spawn {
+        underneath: char,
+        column: usize,
+    },
+}
+
+enum Section {
+    None,
+    Legend,
+    Map,
+}
+
+/// The 1-based column of a byte offset into the line, counting characters
+/// rather than bytes
+fn column_of(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// Splits a legend line into tokens along with their 1-based starting column,
+/// keeping quoted runes such as `' '` together
+fn tokenize(line: &str) -> Vec<(usize, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+
+        if chars[i] == '\'' && i + 2 < chars.len() && chars[i + 2] == '\'' {
+            i += 3;
+        } else {
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+        }
+
+        tokens.push((start + 1, chars[start..i].iter().collect()));
+    }
+
+    tokens
+}
+
+fn parse_shape(name: &str) -> Option<Shape> {
+    match name {
+        "floor" => Some(Shape::Floor),
+        "small" => Some(Shape::Small),
+        "medium" => Some(Shape::Medium),
+        "full_block" => Some(Shape::FullBlock),
+        _ => None,
+    }
+}
+
+fn layer_for(shape: &Shape) -> components::DrawLayer {
+    match shape {
+        Shape::Floor => components::DL_FLOOR,
+        Shape::FullBlock => components::DL_WALLS,
+        Shape::Small | Shape::Medium => components::DL_ENTITY,
+    }
+}
+
+fn parse_rune(token: &str) -> Option<char> {
+    let chars: Vec<char> = token.chars().collect();
+
+    if chars.len() == 3 && chars[0] == '\'' && chars[2] == '\'' {
+        Some(chars[1])
+    } else {
+        None
+    }
+}
+
+fn parse_color(token: &str) -> Option<(u8, u8, u8)> {
+    let parts: Vec<&str> = token.split(',').collect();
+
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let r = parts[0].parse().ok()?;
+    let g = parts[1].parse().ok()?;
+    let b = parts[2].parse().ok()?;
+
+    Some((r, g, b))
+}
+
+fn parse_legend_entry(line_number: usize, line: &str) -> Result<(char, LegendEntry), Error> {
+    let error = |column: usize, kind: ErrorKind| Error {
+        line: line_number,
+        column,
+        kind,
+    };
+
+    let tokens = tokenize(line);
+
+    if tokens.len() < 3 || tokens[0].1.chars().count() != 1 || tokens[1].1 != "=" {
+        return Err(error(
+            tokens.first().map_or(1, |t| t.0),
+            ErrorKind::InvalidLegendEntry(String::from("expected '<glyph> = ...'")),
+        ));
+    }
+
+    let glyph = tokens[0].1.chars().next().unwrap();
+    let values = &tokens[2..];
+
+    if values[0].1 == "spawn" {
+        if values.len() != 2 || values[1].1.chars().count() != 1 {
+            return Err(error(
+                values[0].0,
+                ErrorKind::InvalidLegendEntry(String::from(
+                    "expected 'spawn <glyph of tile underneath>'",
+                )),
+            ));
+        }
+
+        return Ok((
+            glyph,
+            LegendEntry::Spawn {
+                underneath: values[1].1.chars().next().unwrap(),
+                column: values[1].0,
+            },
+        ));
+    }
+
+    if values.len() != 5 {
+        return Err(error(
+            values[0].0,
+            ErrorKind::InvalidLegendEntry(String::from(
+                "expected '<material> <shape> <rune> <fg> <bg>'",
+            )),
+        ));
+    }
+
+    let (material_col, material_name) = &values[0];
+    let (shape_col, shape_name) = &values[1];
+    let (rune_col, rune_token) = &values[2];
+    let (fg_col, fg_token) = &values[3];
+    let (bg_col, bg_token) = &values[4];
+
+    let material = material::by_name(material_name).ok_or_else(|| {
+        error(
+            *material_col,
+            ErrorKind::UnknownMaterial(material_name.clone()),
+        )
+    })?;
+    let shape = parse_shape(shape_name)
+        .ok_or_else(|| error(*shape_col, ErrorKind::UnknownShape(shape_name.clone())))?;
+    let rune = parse_rune(rune_token)
+        .ok_or_else(|| error(*rune_col, ErrorKind::InvalidRune(rune_token.clone())))?;
+    let fg = parse_color(fg_token)
+        .ok_or_else(|| error(*fg_col, ErrorKind::InvalidColor(fg_token.clone())))?;
+    let bg = parse_color(bg_token)
+        .ok_or_else(|| error(*bg_col, ErrorKind::InvalidColor(bg_token.clone())))?;
+
+    let sprite = Sprite {
+        fg_r: fg.0,
+        fg_g: fg.1,
+        fg_b: fg.2,
+        bg_r: bg.0,
+        bg_g: bg.1,
+        bg_b: bg.2,
+        layer: layer_for(&shape),
+        rune,
+    };
+
+    Ok((
+        glyph,
+        LegendEntry::Tile(Tile {
+            material,
+            shape,
+            sprite,
+        }),
+    ))
+}
+
+pub fn parse(contents: &str) -> Result<Level, Error> {
+    let mut section = Section::None;
+    let mut legend: HashMap<char, (usize, LegendEntry)> = HashMap::new();
+    let mut map_header_line = None;
+    let mut map_lines: Vec<(usize, &str)> = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+
+        if let Section::Map = section {
+            map_lines.push((line_number, line));
+            continue;
+        }
+
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let name = &trimmed[1..trimmed.len() - 1];
+
+            section = match name {
+                "legend" => Section::Legend,
+                "map" => {
+                    map_header_line = Some(line_number);
+                    Section::Map
+                }
+                _ => {
+                    return Err(Error {
+                        line: line_number,
+                        column: column_of(line, line.find('[').unwrap()),
+                        kind: ErrorKind::UnknownSection(String::from(name)),
+                    })
+                }
+            };
+
+            continue;
+        }
+
+        match section {
+            Section::Legend => {
+                let (glyph, entry) = parse_legend_entry(line_number, line)?;
+
+                if legend.contains_key(&glyph) {
+                    return Err(Error {
+                        line: line_number,
+                        column: column_of(line, line.find(glyph).unwrap()),
+                        kind: ErrorKind::DuplicateGlyph(glyph),
+                    });
+                }
+
+                legend.insert(glyph, (line_number, entry));
+            }
+
+            _ => {
+                return Err(Error {
+                    line: line_number,
+                    column: 1,
+                    kind: ErrorKind::InvalidLegendEntry(String::from(
+                        "entries must be inside a [legend] section",
+                    )),
+                })
+            }
+        }
+    }
+
+    let map_header_line = map_header_line.ok_or(Error {
+        line: contents.lines().count() + 1,
+        column: 1,
+        kind: ErrorKind::MissingMap,
+    })?;
+
+    // Make sure any spawn entries refer to real tiles before we start placing them
+    for (line_number, entry) in legend.values() {
+        if let LegendEntry::Spawn { underneath, column } = entry {
+            match legend.get(underneath) {
+                Some((_, LegendEntry::Tile(_))) => (),
+                _ => {
+                    return Err(Error {
+                        line: *line_number,
+                        column: *column,
+                        kind: ErrorKind::UnknownGlyph(*underneath),
+                    })
+                }
+            }
+        }
+    }
+
+    let mut tiles = Vec::new();
+    let mut spawn = None;
+
+    for (y, (line_number, line)) in map_lines.iter().enumerate() {
+        for (x, glyph) in line.chars().enumerate() {
+            if glyph == ' ' {
+                continue;
+            }
+
+            let pos = Position::new(x as i32, y as i32);
+            let error = |kind: ErrorKind| Error {
+                line: *line_number,
+                column: x + 1,
+                kind,
+            };
+
+            match legend.get(&glyph) {
+                Some((_, LegendEntry::Tile(tile))) => tiles.push((pos, tile.clone())),
+
+                Some((_, LegendEntry::Spawn { underneath, .. })) => {
+                    if spawn.is_some() {
+                        return Err(error(ErrorKind::MultipleSpawns));
+                    }
+
+                    if let Some((_, LegendEntry::Tile(tile))) = legend.get(underneath) {
+                        tiles.push((pos.clone(), tile.clone()));
+                    }
+
+                    spawn = Some(pos);
+                }
+
+                None => return Err(error(ErrorKind::UnknownGlyph(glyph))),
+            }
+        }
+    }
+
+    let spawn = spawn.ok_or(Error {
+        line: map_header_line,
+        column: 1,
+        kind: ErrorKind::MissingSpawn,
+    })?;
+
+    Ok(Level { tiles, spawn })
+}
+
+pub fn load(path: &std::path::Path) -> Result<Level, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+
+    Ok(parse(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGEND: &str = "[legend]
+X = stone full_block 'X' 255,255,255 128,128,128
+. = stone floor ' ' 255,255,255 28,28,28
+@ = spawn .
+";
+
+    fn parse_map(map: &str) -> Result<Level, Error> {
+        parse(&format!("{}\n[map]\n{}", LEGEND, map))
+    }
+
+    #[test]
+    fn parses_walls_floors_and_spawn() {
+        let level = parse_map("XXX\nX@.\nXXX").unwrap();
+
+        assert_eq!(level.spawn, Position::new(1, 1));
+        assert_eq!(level.tiles.len(), 9);
+
+        let tile_at = |x, y| {
+            level
+                .tiles
+                .iter()
+                .find(|(pos, _)| *pos == Position::new(x, y))
+                .map(|(_, tile)| tile)
+                .unwrap()
+        };
+
+        assert_eq!(*tile_at(0, 0), Tile::stone_wall());
+        assert_eq!(*tile_at(2, 1), Tile::stone_floor());
+
+        // Spawn point gets the tile underneath it
+        assert_eq!(*tile_at(1, 1), Tile::stone_floor());
+    }
+
+    #[test]
+    fn spaces_are_empty() {
+        let level = parse_map("X X\n @ ").unwrap();
+
+        assert_eq!(level.tiles.len(), 3);
+    }
+
+    #[test]
+    fn reports_unknown_glyph_with_position() {
+        let result = parse_map("XXX\nX@.\nX?X");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 9,
+                column: 2,
+                kind: ErrorKind::UnknownGlyph('?'),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_missing_spawn_at_map_header() {
+        let result = parse_map("XXX\nX.X\nXXX");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 6,
+                column: 1,
+                kind: ErrorKind::MissingSpawn,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_multiple_spawns() {
+        let result = parse_map("X@X\nX@X");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 8,
+                column: 2,
+                kind: ErrorKind::MultipleSpawns,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_unknown_material_with_column() {
+        let result = parse("[legend]\nX = cheese full_block 'X' 1,2,3 4,5,6\n[map]\n@");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 2,
+                column: 5,
+                kind: ErrorKind::UnknownMaterial(String::from("cheese")),
+            })
+        );
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let result = parse("\u{3000}\u{3000}[nope]\n");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 1,
+                column: 3,
+                kind: ErrorKind::UnknownSection(String::from("nope")),
+            })
+        );
+
+        let result = parse(
+            "[legend]\né = stone floor ' ' 1,2,3 4,5,6\n\u{3000}é = stone floor ' ' 1,2,3 4,5,6",
+        );
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 3,
+                column: 2,
+                kind: ErrorKind::DuplicateGlyph('é'),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_unknown_spawn_tile_at_its_glyph() {
+        let result = parse("[legend]\n  @ = spawn ?\n[map]\n@");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 2,
+                column: 13,
+                kind: ErrorKind::UnknownGlyph('?'),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_invalid_color() {
+        let result = parse("[legend]\nX = stone full_block 'X' 1,2 4,5,6\n[map]\n@");
+
+        assert_eq!(
+            result,
+            Err(Error {
+                line: 2,
+                column: 26,
+                kind: ErrorKind::InvalidColor(String::from("1,2")),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_missing_map_section() {
+        let result = parse(LEGEND);
+
+        assert_eq!(result.unwrap_err().kind, ErrorKind::MissingMap);
+    }
+
+    #[test]
+    fn displays_line_and_column() {
+        let error = Error {
+            line: 3,
+            column: 7,
+            kind: ErrorKind::UnknownGlyph('?'),
+        };
+
+        assert_eq!(
+            format!("{}", error),
+            "line 3, column 7: glyph '?' is not in the legend"
+        );
+    }
+
+    #[test]
+    fn parses_default_level() {
+        let level = parse(super::super::DEFAULT_LEVEL).unwrap();
+
+        assert!(!level.tiles.is_empty());
+    }
+}
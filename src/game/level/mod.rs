@@ -0,0 +1,170 @@
+use super::components::{self, material::Material, Position, Shape, Sprite};
+use std::collections::HashSet;
+
+pub mod cave;
+pub mod dungeon;
+pub mod file;
+
+/// Everything needed to spawn a single map entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tile {
+    pub material: Material,
+    pub shape: Shape,
+    pub sprite: Sprite,
+}
+
+impl Tile {
+    pub fn stone_wall() -> Tile {
+        Tile {
+            material: components::material::stone(),
+            shape: Shape::FullBlock,
+            sprite: Sprite {
+                fg_r: 255,
+                fg_g: 255,
+                fg_b: 255,
+                bg_r: 128,
+                bg_g: 128,
+                bg_b: 128,
+                layer: components::DL_WALLS,
+                rune: 'X',
+            },
+        }
+    }
+
+    pub fn stone_floor() -> Tile {
+        Tile {
+            material: components::material::stone(),
+            shape: Shape::Floor,
+            sprite: Sprite {
+                fg_r: 255,
+                fg_g: 255,
+                fg_b: 255,
+                bg_r: 28,
+                bg_g: 28,
+                bg_b: 28,
+                layer: components::DL_FLOOR,
+                rune: ' ',
+            },
+        }
+    }
+
+    /// What a burnt down wall or floor turns into
+    pub fn ash_floor() -> Tile {
+        Tile {
+            material: components::material::ash(),
+            shape: Shape::Floor,
+            sprite: Sprite {
+                fg_r: 110,
+                fg_g: 105,
+                fg_b: 100,
+                bg_r: 36,
+                bg_g: 32,
+                bg_b: 30,
+                layer: components::DL_FLOOR,
+                rune: '.',
+            },
+        }
+    }
+}
+
+/// A fully described map, ready to be turned into entities
+#[derive(Clone, Debug, PartialEq)]
+pub struct Level {
+    pub tiles: Vec<(Position, Tile)>,
+    pub spawn: Position,
+}
+
+/// Surrounds every floor with walls and turns both into a level.  Tiles are
+/// sorted so that the same floors always produce entities in the same order.
+fn build_level(floors: &HashSet<Position>, spawn: Position) -> Level {
+    let mut walls = HashSet::new();
+
+    for floor in floors {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let neighbor = Position::new(floor.x + x, floor.y + y);
+
+                if !floors.contains(&neighbor) {
+                    walls.insert(neighbor);
+                }
+            }
+        }
+    }
+
+    let mut floors: Vec<Position> = floors.iter().cloned().collect();
+    let mut walls: Vec<Position> = walls.into_iter().collect();
+
+    floors.sort_by_key(|pos| (pos.y, pos.x));
+    walls.sort_by_key(|pos| (pos.y, pos.x));
+
+    let wall = Tile::stone_wall();
+    let floor = Tile::stone_floor();
+
+    let tiles = walls
+        .into_iter()
+        .map(|pos| (pos, wall.clone()))
+        .chain(floors.into_iter().map(|pos| (pos, floor.clone())))
+        .collect();
+
+    Level { tiles, spawn }
+}
+
+/// The hand-built starting room, bundled as an example of the level file format
+pub const DEFAULT_LEVEL: &str = include_str!("../../../levels/default.txt");
+
+/// Where a level comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelConfig {
+    File(std::path::PathBuf),
+    Dungeon { seed: u64 },
+    Cave { seed: u64 },
+}
+
+impl LevelConfig {
+    pub fn build(&self) -> Result<Level, Box<dyn std::error::Error>> {
+        match self {
+            LevelConfig::File(path) => file::load(path),
+            LevelConfig::Dungeon { seed } => {
+                Ok(dungeon::generate(*seed, &dungeon::Settings::default()))
+            }
+            LevelConfig::Cave { seed } => Ok(cave::generate(*seed, &cave::Settings::default())),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_level {
+    use super::super::components::{Position, Shape};
+    use super::Level;
+    use std::collections::HashSet;
+
+    pub fn floors_of(level: &Level) -> HashSet<Position> {
+        level
+            .tiles
+            .iter()
+            .filter(|(_, tile)| tile.shape == Shape::Floor)
+            .map(|(pos, _)| pos.clone())
+            .collect()
+    }
+
+    /// Every floor that can be walked to from the spawn without going
+    /// diagonally
+    pub fn reachable_from_spawn(level: &Level) -> HashSet<Position> {
+        let floors = floors_of(level);
+        let mut seen = HashSet::new();
+        let mut open = vec![level.spawn.clone()];
+
+        while let Some(pos) = open.pop() {
+            if !floors.contains(&pos) || !seen.insert(pos.clone()) {
+                continue;
+            }
+
+            open.push(pos.up());
+            open.push(pos.down());
+            open.push(pos.left());
+            open.push(pos.right());
+        }
+
+        seen
+    }
+}
@@ -1,73 +1,325 @@
 pub mod components;
 pub mod entities;
+pub mod fov;
 pub mod input;
+pub mod level;
+pub mod pathfinding;
 pub mod resources;
+pub mod save;
 pub mod systems;
 
 use input::Action;
 
 use systems::{
+    camera::Camera,
     collisions_solid::CollisionsSolid,
+    damage::Damage,
+    fire::Fire,
+    fluid_drag::FluidDrag,
+    fluid_spread::FluidSpread,
+    items::ItemActions,
+    lighting::Lighting,
+    melee::Melee,
+    monster_ai::MonsterAi,
     movement_apply::MovementApply,
+    player_flow_field::PlayerFlowField,
     player_input::PlayerInput,
     render::{Render, Renderer},
+    scheduler::Scheduler,
     sync_game_map::SyncGameMap,
     visibility::Visibility,
 };
 
+use rand::SeedableRng;
 use specs::{DispatcherBuilder, World, WorldExt};
 
-pub fn run<T: Renderer, U: input::Buffer>(renderer: T, mut input: U) {
+/// Roughly how many floor tiles there are for each monster
+const FLOORS_PER_MONSTER: usize = 150;
+
+/// Roughly how many floor tiles there are for each item lying around
+const FLOORS_PER_ITEM: usize = 300;
+
+/// How a game begins
+pub enum Start {
+    /// A fresh game on the level, with everything in it placed using the seed
+    New { level: level::Level, seed: u64 },
+
+    /// Carrying on from where a saved game left off
+    Continue(save::SaveFile),
+}
+
+/// How a game ended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// Quit with the player still alive, so there's a save to come back to
+    Saved(std::path::PathBuf),
+
+    /// Quit after the player died, which leaves nothing worth saving
+    Died,
+}
+
+/// Plays until the player quits.  A continued game is saved back over the
+/// file it came from, while new games never touch existing files and are saved
+/// next to the given path if it's taken.  Nothing is saved once the player has
+/// died, and no save file ever gets deleted.
+pub fn run<T: Renderer, U: input::Buffer>(
+    renderer: T,
+    mut input: U,
+    start: Start,
+    save_path: &std::path::Path,
+) -> Result<Exit, save::Error> {
     let mut world = World::new();
+    let continued = matches!(start, Start::Continue(_));
 
     world.insert(resources::DeltaTime(std::time::Duration::from_secs(1)));
     world.insert(resources::CameraCenter { x: 0, y: 0 });
+    world.insert(resources::CameraMode::Deadzone {
+        width: 16,
+        height: 8,
+    });
     world.insert(resources::PendingAction(None));
+    world.insert(resources::InventoryScreen::default());
+    world.insert(resources::DamageEvents::default());
+    world.insert(resources::game_log::GameLog::default());
 
     let render = Render::new(renderer);
 
     let mut dispatcher = DispatcherBuilder::new()
-        .with(PlayerInput, "player_input", &[])
-        .with(SyncGameMap, "sync_game_map", &[])
-        .with(
-            CollisionsSolid,
-            "collisions_solid",
-            &["sync_game_map", "player_input"],
-        )
+        .with(Scheduler, "scheduler", &[])
+        .with(PlayerInput, "player_input", &["scheduler"])
+        .with(MonsterAi, "monster_ai", &["scheduler"])
+        .with(ItemActions, "item_actions", &["scheduler"])
+        .with(Fire, "fire", &["scheduler"])
+        .with(Melee, "melee", &["player_input", "monster_ai"])
+        .with(Damage, "damage", &["melee", "fire"])
+        .with(CollisionsSolid, "collisions_solid", &["melee", "damage"])
+        .with(FluidDrag, "fluid_drag", &["collisions_solid"])
+        .with(FluidSpread, "fluid_spread", &["scheduler"])
         .with(
             MovementApply,
             "movement_apply",
-            &["player_input", "collisions_solid"],
+            &["player_input", "monster_ai", "collisions_solid"],
+        )
+        // Synced after everything has moved, so the rest of this dispatch and
+        // the start of the next one see where things actually are
+        .with(
+            SyncGameMap::default(),
+            "sync_game_map",
+            &["movement_apply", "fluid_spread", "fire", "item_actions"],
+        )
+        .with(Lighting, "lighting", &["sync_game_map"])
+        .with(
+            Visibility::default(),
+            "visibility",
+            &["sync_game_map", "lighting"],
         )
-        .with(Visibility, "visibility", &["movement_apply"])
+        .with(Camera, "camera", &["movement_apply"])
+        .with(PlayerFlowField, "player_flow_field", &["sync_game_map"])
         .with_thread_local(render)
         .build();
 
     dispatcher.setup(&mut world);
 
-    entities::player::create_in(&mut world, components::Position::new(0, 0));
-    entities::map::create_in(&mut world);
+    match start {
+        Start::New { level, seed } => {
+            world.insert(resources::GameRng(rand_pcg::Pcg32::seed_from_u64(seed)));
+
+            entities::player::create_in(&mut world, level.spawn.clone());
+            entities::map::create_in(&mut world, &level);
+
+            let floors = level
+                .tiles
+                .iter()
+                .filter(|(_, tile)| tile.shape == components::Shape::Floor)
+                .count();
+
+            entities::monster::populate(&mut world, &level, floors / FLOORS_PER_MONSTER);
+            entities::item::populate(&mut world, &level, floors / FLOORS_PER_ITEM);
+
+            world.write_resource::<resources::game_log::GameLog>().push(
+                String::from("Welcome! Bump into monsters to fight them."),
+                resources::game_log::WHITE,
+            );
+            world.write_resource::<resources::game_log::GameLog>().push(
+                String::from("Press g to pick things up and i to see what you carry."),
+                resources::game_log::WHITE,
+            );
+        }
+        Start::Continue(save) => {
+            save::restore(&mut world, save)?;
+
+            world
+                .write_resource::<resources::game_log::GameLog>()
+                .push(String::from("Welcome back!"), resources::game_log::WHITE);
+        }
+    }
+
+    let mut queued: Vec<Action> = Vec::new();
+    let mut waiting = false;
 
     loop {
-        {
-            let mut pending_actions = input.step();
+        queued.extend(input.step());
+
+        // Keep the game for next time, unless there's nothing left to keep
+        if queued.contains(&Action::HardExit) {
+            let player = world.read_resource::<resources::Player>().ent;
+            let alive = world
+                .read_storage::<components::Health>()
+                .get(player)
+                .is_some_and(|health| !health.is_dead());
+
+            if alive {
+                let path = if continued {
+                    save_path.to_path_buf()
+                } else {
+                    save::unused_path(save_path)
+                };
+
+                save::write(&world, &path)?;
+
+                return Ok(Exit::Saved(path));
+            }
+
+            return Ok(Exit::Died);
+        }
+
+        // Scrolling the log and looking through the inventory don't wait on
+        // anything, but do need a redraw
+        let mut redraw = false;
+
+        let carrying = {
+            let player = world.read_resource::<resources::Player>().ent;
 
-            for action in &pending_actions {
-                match action {
-                    // Just nope out
-                    Action::HardExit => return,
-                    _ => (),
+            world
+                .read_storage::<components::Inventory>()
+                .get(player)
+                .map_or(0, |inventory| inventory.items.len())
+        };
+
+        queued.retain(|action| {
+            let mut log = world.write_resource::<resources::game_log::GameLog>();
+            let mut screen = world.write_resource::<resources::InventoryScreen>();
+
+            match action {
+                Action::ScrollLogUp => log.scroll_up(1),
+                Action::ScrollLogDown => log.scroll_down(1),
+                Action::Inventory => screen.open = !screen.open,
+
+                // With the inventory open, moving picks out an item instead
+                Action::Up if screen.open => screen.select_previous(),
+                Action::Down if screen.open => screen.select_next(carrying),
+                Action::Left | Action::Right if screen.open => (),
+
+                // Doing anything with an item needs the inventory open first,
+                // so there's an item picked out to do it with
+                Action::Drop | Action::Use | Action::Equip | Action::Unequip if !screen.open => {
+                    screen.open = true
                 }
+                _ => return true,
             }
 
-            let mut action_writer = world.write_resource::<resources::PendingAction>();
+            redraw = true;
+            false
+        });
 
-            *action_writer = resources::PendingAction(pending_actions.pop());
+        // Hold on to input until the player can actually do something with it
+        let action = if world.read_resource::<resources::Turn>().player_ready && !queued.is_empty()
+        {
+            Some(queued.remove(0))
+        } else {
+            None
+        };
+
+        // Nothing changes while the world waits on the player
+        if waiting && action.is_none() && !redraw {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            continue;
         }
 
-        dispatcher.dispatch(&mut world);
+        *world.write_resource::<resources::PendingAction>() = resources::PendingAction(action);
+
+        dispatcher.dispatch(&world);
         world.maintain();
 
-        std::thread::sleep(std::time::Duration::from_millis(10));
+        // Keep going while anyone but the player still has something to do,
+        // and stop for good once the player is dead
+        let turn = world.read_resource::<resources::Turn>();
+        let player = world.read_resource::<resources::Player>().ent;
+        let player_dead = world
+            .read_storage::<components::Health>()
+            .get(player)
+            .is_some_and(|health| health.is_dead());
+
+        waiting = player_dead
+            || turn.player_ready
+                && turn.ready.is_empty()
+                && world
+                    .read_resource::<resources::PendingAction>()
+                    .0
+                    .is_none();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use systems::render::Color;
+
+    struct Headless;
+
+    impl Renderer for Headless {
+        fn draw_at(&mut self, _: usize, _: usize, _: &Color, _: &Color, _: char) {}
+
+        fn size(&self) -> (usize, usize) {
+            (80, 24)
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    /// Lets the world run for a few steps and then quits
+    struct QuitAfter(usize);
+
+    impl input::Buffer for QuitAfter {
+        fn step(&mut self) -> Vec<Action> {
+            if self.0 == 0 {
+                return vec![Action::HardExit];
+            }
+
+            self.0 -= 1;
+            Vec::new()
+        }
+    }
+
+    fn new_game() -> Start {
+        Start::New {
+            level: level::file::parse(level::DEFAULT_LEVEL).unwrap(),
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn new_games_leave_earlier_saves_alone() {
+        let dir = std::env::temp_dir().join(format!("adventure-run-{}", std::process::id()));
+        let path = dir.join("adventure.save");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "earlier").unwrap();
+
+        let exit = run(Headless, QuitAfter(3), new_game(), &path).unwrap();
+        let saved = dir.join("adventure-2.save");
+
+        assert_eq!(exit, Exit::Saved(saved.clone()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "earlier");
+
+        // Carrying on saves back over the same file
+        let save = save::read(&saved).unwrap();
+        let exit = run(Headless, QuitAfter(3), Start::Continue(save), &saved).unwrap();
+
+        assert_eq!(exit, Exit::Saved(saved.clone()));
+        assert!(save::read(&saved).is_ok());
+        assert!(!dir.join("adventure-3.save").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
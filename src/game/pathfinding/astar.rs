@@ -0,0 +1,288 @@
+use super::super::components::Position;
+use super::super::resources::game_map::GameMap;
+use super::{
+    is_walkable, neighbors, Movement, PathError, PathOptions, DIAGONAL_COST, STRAIGHT_COST,
+};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+fn heuristic(from: &Position, to: &Position, movement: Movement) -> u32 {
+    let dx = (from.x - to.x).unsigned_abs();
+    let dy = (from.y - to.y).unsigned_abs();
+
+    match movement {
+        Movement::FourWay => (dx + dy) * STRAIGHT_COST,
+        Movement::EightWay => {
+            let diagonal = dx.min(dy);
+            let straight = dx.max(dy) - diagonal;
+
+            diagonal * DIAGONAL_COST + straight * STRAIGHT_COST
+        }
+    }
+}
+
+/// Finds the cheapest path where every tile costs the same to enter
+pub fn find_path(
+    map: &GameMap,
+    from: &Position,
+    to: &Position,
+    options: &PathOptions,
+) -> Result<Vec<Position>, PathError> {
+    find_path_with_cost(map, from, to, options, |_| 1)
+}
+
+/// Finds the cheapest path from one position to another.  The cost function
+/// says how expensive each tile is to step onto, where 1 is a normal tile and
+/// anything lower is treated as 1.  The returned path leaves out the start
+/// and ends with the goal.
+pub fn find_path_with_cost<F: Fn(&Position) -> u32>(
+    map: &GameMap,
+    from: &Position,
+    to: &Position,
+    options: &PathOptions,
+    cost: F,
+) -> Result<Vec<Position>, PathError> {
+    if from == to {
+        return Ok(Vec::new());
+    }
+
+    if !is_walkable(map, to) {
+        return Err(PathError::Unreachable);
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut best_cost: HashMap<Position, u32> = HashMap::new();
+    let mut came_from: HashMap<Position, Position> = HashMap::new();
+    let mut explored = 0;
+
+    best_cost.insert(from.clone(), 0);
+    open.push((
+        Reverse(heuristic(from, to, options.movement)),
+        Reverse(0),
+        from.x,
+        from.y,
+    ));
+
+    while let Some((_, Reverse(cost_so_far), x, y)) = open.pop() {
+        let current = Position::new(x, y);
+
+        if current == *to {
+            let mut path = vec![current];
+
+            while let Some(previous) = came_from.get(path.last().unwrap()) {
+                if previous == from {
+                    break;
+                }
+
+                path.push(previous.clone());
+            }
+
+            path.reverse();
+
+            return Ok(path);
+        }
+
+        // Skip stale entries that were already reached more cheaply
+        if best_cost
+            .get(&current)
+            .is_some_and(|best| *best < cost_so_far)
+        {
+            continue;
+        }
+
+        explored += 1;
+
+        if explored > options.max_nodes {
+            return Err(PathError::BudgetExceeded);
+        }
+
+        for (next, step_cost) in neighbors(map, &current, options.movement) {
+            let next_cost = cost_so_far + step_cost * cost(&next).max(1);
+
+            if best_cost.get(&next).map_or(true, |best| next_cost < *best) {
+                let estimate = next_cost + heuristic(&next, to, options.movement);
+
+                best_cost.insert(next.clone(), next_cost);
+                came_from.insert(next.clone(), current.clone());
+                open.push((Reverse(estimate), Reverse(next_cost), next.x, next.y));
+            }
+        }
+    }
+
+    Err(PathError::Unreachable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_map;
+    use super::*;
+
+    fn four_way() -> PathOptions {
+        PathOptions {
+            movement: Movement::FourWay,
+            ..PathOptions::default()
+        }
+    }
+
+    #[test]
+    fn empty_path_when_already_there() {
+        let map = test_map::build(&["..."]);
+        let pos = Position::new(1, 0);
+
+        assert_eq!(
+            find_path(&map, &pos, &pos, &PathOptions::default()),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn walks_straight_line() {
+        let map = test_map::build(&["....."]);
+
+        let path = find_path(
+            &map,
+            &Position::new(0, 0),
+            &Position::new(4, 0),
+            &PathOptions::default(),
+        );
+
+        assert_eq!(
+            path,
+            Ok(vec![
+                Position::new(1, 0),
+                Position::new(2, 0),
+                Position::new(3, 0),
+                Position::new(4, 0),
+            ])
+        );
+    }
+
+    #[test]
+    fn walks_around_walls() {
+        let map = test_map::build(&[
+            ".....", //
+            ".###.", //
+            ".#...", //
+            ".#.#.", //
+        ]);
+
+        let path = find_path(
+            &map,
+            &Position::new(0, 3),
+            &Position::new(2, 3),
+            &four_way(),
+        )
+        .unwrap();
+
+        assert_eq!(path.len(), 12);
+        assert_eq!(*path.last().unwrap(), Position::new(2, 3));
+
+        for step in &path {
+            assert!(is_walkable(&map, step));
+        }
+    }
+
+    #[test]
+    fn eight_way_takes_diagonals() {
+        let map = test_map::build(&[
+            "....", //
+            "....", //
+            "....", //
+            "....", //
+        ]);
+        let from = Position::new(0, 0);
+        let to = Position::new(3, 3);
+
+        assert_eq!(
+            find_path(&map, &from, &to, &PathOptions::default())
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(find_path(&map, &from, &to, &four_way()).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn does_not_cut_corners() {
+        let map = test_map::build(&[
+            ".#", //
+            "..", //
+        ]);
+
+        let path = find_path(
+            &map,
+            &Position::new(0, 0),
+            &Position::new(1, 1),
+            &PathOptions::default(),
+        );
+
+        assert_eq!(path, Ok(vec![Position::new(0, 1), Position::new(1, 1)]));
+    }
+
+    #[test]
+    fn unreachable_when_walled_off() {
+        let map = test_map::build(&[
+            ".#...", //
+            "##...", //
+            ".....", //
+        ]);
+
+        let path = find_path(
+            &map,
+            &Position::new(0, 0),
+            &Position::new(4, 2),
+            &PathOptions::default(),
+        );
+
+        assert_eq!(path, Err(PathError::Unreachable));
+    }
+
+    #[test]
+    fn unreachable_when_goal_blocked() {
+        let map = test_map::build(&["..#"]);
+
+        let path = find_path(
+            &map,
+            &Position::new(0, 0),
+            &Position::new(2, 0),
+            &PathOptions::default(),
+        );
+
+        assert_eq!(path, Err(PathError::Unreachable));
+    }
+
+    #[test]
+    fn gives_up_when_over_budget() {
+        let map = test_map::build(&["...................."]);
+        let options = PathOptions {
+            max_nodes: 5,
+            ..PathOptions::default()
+        };
+
+        let path = find_path(&map, &Position::new(0, 0), &Position::new(19, 0), &options);
+
+        assert_eq!(path, Err(PathError::BudgetExceeded));
+    }
+
+    #[test]
+    fn avoids_expensive_tiles() {
+        let map = test_map::build(&[
+            "...", //
+            "...", //
+            "...", //
+        ]);
+        let expensive = Position::new(1, 1);
+
+        let path = find_path_with_cost(
+            &map,
+            &Position::new(1, 0),
+            &Position::new(1, 2),
+            &four_way(),
+            |pos| if *pos == expensive { 10 } else { 1 },
+        )
+        .unwrap();
+
+        assert_eq!(path.len(), 4);
+        assert!(!path.contains(&expensive));
+    }
+}
@@ -0,0 +1,230 @@
+use super::super::components::Position;
+use super::super::resources::game_map::GameMap;
+use super::{neighbors, Movement};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// How much further away fleeing entities are willing to look for an escape,
+/// as a fraction of 10.  Anything above 10 lets them run past the goal into
+/// open space instead of getting stuck in corners.
+const FLEE_FACTOR: i32 = 12;
+
+/// Cost to reach the nearest goal from every tile within range.  Any number of
+/// entities can walk downhill on the same map to reach the goals, which is much
+/// cheaper than finding a path for each one.
+#[derive(Clone, Debug, Default)]
+pub struct DijkstraMap {
+    costs: HashMap<Position, i32>,
+}
+
+impl DijkstraMap {
+    /// Builds a map out to max_cost from the goals, using the same step costs as A*
+    pub fn new(
+        map: &GameMap,
+        goals: &[Position],
+        movement: Movement,
+        max_cost: u32,
+    ) -> DijkstraMap {
+        let seeds = goals.iter().map(|goal| (goal.clone(), 0)).collect();
+
+        DijkstraMap {
+            costs: relax(map, seeds, movement, max_cost as i32),
+        }
+    }
+
+    /// Builds a map that leads away from the goals of this one.  Stepping
+    /// downhill on it moves away from the goals, preferring routes that lead
+    /// somewhere open rather than into a dead end.
+    pub fn inverted(&self, map: &GameMap, movement: Movement) -> DijkstraMap {
+        let seeds = self
+            .costs
+            .iter()
+            .map(|(pos, cost)| (pos.clone(), -cost * FLEE_FACTOR / 10))
+            .collect();
+
+        DijkstraMap {
+            costs: relax(map, seeds, movement, 0),
+        }
+    }
+
+    pub fn cost(&self, pos: &Position) -> Option<i32> {
+        self.costs.get(pos).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.costs.is_empty()
+    }
+
+    /// The neighboring tile with the lowest cost, if any are lower than where we are
+    pub fn step_from(&self, pos: &Position, movement: Movement) -> Option<Position> {
+        let current = self.cost(pos)?;
+        let mut best: Option<(Position, i32)> = None;
+
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let diagonal = x != 0 && y != 0;
+
+                if (x == 0 && y == 0) || (diagonal && movement == Movement::FourWay) {
+                    continue;
+                }
+
+                // Same rule as pathfinding, no squeezing past corners
+                if diagonal
+                    && (self.cost(&Position::new(pos.x + x, pos.y)).is_none()
+                        || self.cost(&Position::new(pos.x, pos.y + y)).is_none())
+                {
+                    continue;
+                }
+
+                let next = Position::new(pos.x + x, pos.y + y);
+
+                if let Some(cost) = self.cost(&next) {
+                    if cost < current && best.as_ref().map_or(true, |(_, best)| cost < *best) {
+                        best = Some((next, cost));
+                    }
+                }
+            }
+        }
+
+        best.map(|(pos, _)| pos)
+    }
+}
+
+/// Dijkstra's algorithm from many starting points at once, each with its own
+/// starting cost.  Stops spreading once costs go above max_cost.
+fn relax(
+    map: &GameMap,
+    seeds: Vec<(Position, i32)>,
+    movement: Movement,
+    max_cost: i32,
+) -> HashMap<Position, i32> {
+    let mut costs: HashMap<Position, i32> = HashMap::new();
+    let mut open = BinaryHeap::new();
+
+    for (pos, cost) in seeds {
+        if costs.get(&pos).map_or(true, |existing| cost < *existing) {
+            costs.insert(pos.clone(), cost);
+            open.push((Reverse(cost), pos.x, pos.y));
+        }
+    }
+
+    while let Some((Reverse(cost), x, y)) = open.pop() {
+        let current = Position::new(x, y);
+
+        if costs.get(&current).is_some_and(|best| *best < cost) {
+            continue;
+        }
+
+        for (next, step_cost) in neighbors(map, &current, movement) {
+            let next_cost = cost + step_cost as i32;
+
+            if next_cost > max_cost {
+                continue;
+            }
+
+            if costs.get(&next).map_or(true, |best| next_cost < *best) {
+                costs.insert(next.clone(), next_cost);
+                open.push((Reverse(next_cost), next.x, next.y));
+            }
+        }
+    }
+
+    costs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_map, STRAIGHT_COST};
+    use super::*;
+
+    #[test]
+    fn cost_grows_away_from_goal() {
+        let map = test_map::build(&["....."]);
+        let dijkstra = DijkstraMap::new(&map, &[Position::new(0, 0)], Movement::EightWay, 1000);
+
+        for x in 0..5 {
+            assert_eq!(
+                dijkstra.cost(&Position::new(x, 0)),
+                Some(x * STRAIGHT_COST as i32)
+            );
+        }
+    }
+
+    #[test]
+    fn uses_nearest_of_many_goals() {
+        let map = test_map::build(&["......."]);
+        let dijkstra = DijkstraMap::new(
+            &map,
+            &[Position::new(0, 0), Position::new(6, 0)],
+            Movement::FourWay,
+            1000,
+        );
+
+        assert_eq!(dijkstra.cost(&Position::new(1, 0)), Some(10));
+        assert_eq!(dijkstra.cost(&Position::new(5, 0)), Some(10));
+        assert_eq!(dijkstra.cost(&Position::new(3, 0)), Some(30));
+    }
+
+    #[test]
+    fn stops_at_max_cost() {
+        let map = test_map::build(&["......."]);
+        let dijkstra = DijkstraMap::new(&map, &[Position::new(0, 0)], Movement::FourWay, 20);
+
+        assert_eq!(dijkstra.cost(&Position::new(2, 0)), Some(20));
+        assert_eq!(dijkstra.cost(&Position::new(3, 0)), None);
+    }
+
+    #[test]
+    fn does_not_pass_through_walls() {
+        let map = test_map::build(&[
+            "..#..", //
+            "..#..", //
+        ]);
+        let dijkstra = DijkstraMap::new(&map, &[Position::new(0, 0)], Movement::EightWay, 1000);
+
+        assert_eq!(dijkstra.cost(&Position::new(2, 0)), None);
+        assert_eq!(dijkstra.cost(&Position::new(4, 1)), None);
+    }
+
+    #[test]
+    fn steps_lead_to_goal() {
+        let map = test_map::build(&[
+            ".....", //
+            ".###.", //
+            ".#...", //
+        ]);
+        let goal = Position::new(2, 2);
+        let dijkstra =
+            DijkstraMap::new(&map, std::slice::from_ref(&goal), Movement::EightWay, 1000);
+
+        let mut pos = Position::new(0, 2);
+
+        for _ in 0..20 {
+            match dijkstra.step_from(&pos, Movement::EightWay) {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+
+        assert_eq!(pos, goal);
+        assert_eq!(dijkstra.step_from(&goal, Movement::EightWay), None);
+    }
+
+    #[test]
+    fn inverted_map_steps_away_from_goal() {
+        let map = test_map::build(&["........."]);
+        let goal = Position::new(4, 0);
+        let dijkstra =
+            DijkstraMap::new(&map, std::slice::from_ref(&goal), Movement::EightWay, 1000);
+        let flee = dijkstra.inverted(&map, Movement::EightWay);
+
+        assert_eq!(
+            flee.step_from(&Position::new(5, 0), Movement::EightWay),
+            Some(Position::new(6, 0))
+        );
+        assert_eq!(
+            flee.step_from(&Position::new(3, 0), Movement::EightWay),
+            Some(Position::new(2, 0))
+        );
+    }
+}
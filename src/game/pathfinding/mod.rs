@@ -0,0 +1,115 @@
+use super::components::Position;
+use super::resources::game_map::{GameMap, TileProperties};
+
+pub mod astar;
+pub mod dijkstra;
+
+pub use astar::{find_path, find_path_with_cost};
+pub use dijkstra::DijkstraMap;
+
+/// Cost of a straight step onto a tile with a movement cost of 1
+pub const STRAIGHT_COST: u32 = 10;
+
+/// Cost of a diagonal step onto a tile with a movement cost of 1, roughly 10 * sqrt(2)
+pub const DIAGONAL_COST: u32 = 14;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Movement {
+    FourWay,
+    EightWay,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathOptions {
+    pub movement: Movement,
+
+    /// How many tiles to explore before giving up
+    pub max_nodes: usize,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            movement: Movement::EightWay,
+            max_nodes: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// Every reachable tile was searched without finding the goal
+    Unreachable,
+
+    /// Gave up after searching max_nodes tiles
+    BudgetExceeded,
+}
+
+/// Tiles can be walked on if something is there, like a floor, and nothing blocks them
+pub fn is_walkable(map: &GameMap, pos: &Position) -> bool {
+    map.get_entities(pos).is_some() && !map.tile_is(pos, TileProperties::BLOCKED)
+}
+
+const STRAIGHT: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const DIAGONAL: [(i32, i32); 4] = [(1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+/// Walkable tiles that can be stepped to from the given position, along with the
+/// base cost of the step.  Diagonal steps can't cut around blocked corners.
+pub fn neighbors(map: &GameMap, pos: &Position, movement: Movement) -> Vec<(Position, u32)> {
+    let mut result = Vec::with_capacity(8);
+
+    for (x, y) in STRAIGHT.iter() {
+        let next = Position::new(pos.x + x, pos.y + y);
+
+        if is_walkable(map, &next) {
+            result.push((next, STRAIGHT_COST));
+        }
+    }
+
+    if movement == Movement::EightWay {
+        for (x, y) in DIAGONAL.iter() {
+            let next = Position::new(pos.x + x, pos.y + y);
+
+            if is_walkable(map, &next)
+                && is_walkable(map, &Position::new(pos.x + x, pos.y))
+                && is_walkable(map, &Position::new(pos.x, pos.y + y))
+            {
+                result.push((next, DIAGONAL_COST));
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+pub mod test_map {
+    use super::super::components::Position;
+    use super::super::resources::game_map::{GameMap, TileProperties};
+    use specs::{Builder, World, WorldExt};
+
+    /// Builds a map from rows of text where '#' is a wall, '.' is a floor and
+    /// anything else is left empty
+    pub fn build(rows: &[&str]) -> GameMap {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let mut map = GameMap::new();
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, glyph) in row.chars().enumerate() {
+                let pos = Position::new(x as i32, y as i32);
+
+                match glyph {
+                    '#' => {
+                        map.add(&pos, entity);
+                        map.mark_tile(&pos, TileProperties::BLOCKED | TileProperties::OPAQUE);
+                    }
+                    '.' => map.add(&pos, entity),
+                    _ => (),
+                }
+            }
+        }
+
+        map
+    }
+}
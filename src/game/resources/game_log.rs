@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Older messages are forgotten once there are more than this many
+pub const MAX_MESSAGES: usize = 200;
+
+pub const WHITE: (u8, u8, u8) = (220, 220, 220);
+pub const RED: (u8, u8, u8) = (255, 90, 80);
+pub const ORANGE: (u8, u8, u8) = (255, 160, 60);
+pub const GREEN: (u8, u8, u8) = (110, 220, 110);
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Message {
+    pub text: String,
+    pub color: (u8, u8, u8),
+}
+
+/// Everything the player has been told about what's going on, newest last.
+/// Scrolling back keeps the view on older messages until it's scrolled down
+/// again or something new happens.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GameLog {
+    messages: VecDeque<Message>,
+
+    /// How many messages back from the newest the view is scrolled
+    #[serde(skip)]
+    scroll: usize,
+}
+
+impl GameLog {
+    pub fn push(&mut self, text: String, color: (u8, u8, u8)) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back(Message { text, color });
+        self.scroll = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Looks further back, never past the oldest message
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.messages.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// Up to `count` messages to show with the current scroll, oldest first
+    pub fn visible(&self, count: usize) -> Vec<&Message> {
+        let end = self.messages.len() - self.scroll;
+        let start = end.saturating_sub(count);
+
+        self.messages.range(start..end).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_with(count: usize) -> GameLog {
+        let mut log = GameLog::default();
+
+        for i in 0..count {
+            log.push(format!("message {}", i), WHITE);
+        }
+
+        log
+    }
+
+    fn texts(messages: Vec<&Message>) -> Vec<&str> {
+        messages.iter().map(|m| m.text.as_str()).collect()
+    }
+
+    #[test]
+    fn shows_newest_messages_last() {
+        let log = log_with(5);
+
+        assert_eq!(texts(log.visible(2)), vec!["message 3", "message 4"]);
+        assert_eq!(log.visible(10).len(), 5);
+    }
+
+    #[test]
+    fn scrolls_back_but_not_past_the_start() {
+        let mut log = log_with(5);
+
+        log.scroll_up(2);
+        assert_eq!(texts(log.visible(2)), vec!["message 1", "message 2"]);
+
+        log.scroll_up(100);
+        assert_eq!(texts(log.visible(2)), vec!["message 0"]);
+
+        log.scroll_down(100);
+        assert_eq!(log.scroll(), 0);
+    }
+
+    #[test]
+    fn new_messages_jump_back_to_the_bottom() {
+        let mut log = log_with(5);
+
+        log.scroll_up(3);
+        log.push(String::from("news"), RED);
+
+        assert_eq!(log.scroll(), 0);
+        assert_eq!(texts(log.visible(1)), vec!["news"]);
+    }
+
+    #[test]
+    fn forgets_the_oldest_messages() {
+        let log = log_with(MAX_MESSAGES + 10);
+
+        assert_eq!(log.len(), MAX_MESSAGES);
+        assert_eq!(texts(log.visible(MAX_MESSAGES))[0], "message 10");
+    }
+}
@@ -1,68 +1,256 @@
 use super::super::components::Position;
-use specs::Entity;
+use specs::{world::Index, Entity};
 use std::collections::{HashMap, HashSet};
 
 bitflags! {
     #[derive(Default)]
     pub struct TileProperties: u16 {
         const BLOCKED = 1 << 0;
+        const OPAQUE = 1 << 1;
+        const OCCUPIED = 1 << 2;
+        const ITEMS = 1 << 3;
     }
 }
 
-#[derive(Clone, Default)]
-struct TileData {
+/// Tiles are stored in square chunks of this many tiles per side
+pub const CHUNK_SIZE: i32 = 16;
+
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// A fixed size block of tiles stored as flat arrays, indexed by y * CHUNK_SIZE + x
+#[derive(Clone)]
+struct Chunk {
+    // Properties explicitly set with mark_tile, as opposed to coming from entities
+    marked: Vec<TileProperties>,
+    properties: Vec<TileProperties>,
+
+    // Combined opacity of everything on the tile
+    opacity: Vec<f32>,
+
+    entities: Vec<HashSet<Entity>>,
+}
+
+impl Chunk {
+    fn new() -> Chunk {
+        Chunk {
+            marked: vec![TileProperties::empty(); CHUNK_AREA],
+            properties: vec![TileProperties::empty(); CHUNK_AREA],
+            opacity: vec![0.; CHUNK_AREA],
+            entities: vec![HashSet::new(); CHUNK_AREA],
+        }
+    }
+}
+
+fn chunk_index(coordinate: &Position) -> ((i32, i32), usize) {
+    let chunk = (
+        coordinate.x.div_euclid(CHUNK_SIZE),
+        coordinate.y.div_euclid(CHUNK_SIZE),
+    );
+    let x = coordinate.x.rem_euclid(CHUNK_SIZE);
+    let y = coordinate.y.rem_euclid(CHUNK_SIZE);
+
+    (chunk, (y * CHUNK_SIZE + x) as usize)
+}
+
+#[derive(Clone)]
+struct Placement {
+    entity: Entity,
+    coordinate: Position,
     properties: TileProperties,
-    entities: HashSet<Entity>,
+    opacity: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Bounds {
+    fn expand_to(&mut self, coordinate: &Position) {
+        self.min.x = self.min.x.min(coordinate.x);
+        self.min.y = self.min.y.min(coordinate.y);
+        self.max.x = self.max.x.max(coordinate.x);
+        self.max.y = self.max.y.max(coordinate.y);
+    }
 }
 
 pub struct GameMap {
-    data: HashMap<Position, TileData>,
+    chunks: HashMap<(i32, i32), Chunk>,
+
+    // Indexed by entity id, which specs keeps dense
+    placements: Vec<Option<Placement>>,
+
+    bounds: Option<Bounds>,
 }
 
 impl GameMap {
     pub fn new() -> GameMap {
         GameMap {
-            data: HashMap::with_capacity(5000),
+            chunks: HashMap::new(),
+            placements: Vec::new(),
+            bounds: None,
         }
     }
 
+    fn chunk_mut(&mut self, coordinate: &Position) -> (&mut Chunk, usize) {
+        let (chunk, i) = chunk_index(coordinate);
+
+        (self.chunks.entry(chunk).or_insert_with(Chunk::new), i)
+    }
+
     pub fn add(&mut self, coordinate: &Position, entity: Entity) {
-        let entry = &mut self.data.entry(coordinate.clone()).or_default().entities;
+        let (chunk, i) = self.chunk_mut(coordinate);
+        chunk.entities[i].insert(entity);
+
+        match &mut self.bounds {
+            Some(bounds) => bounds.expand_to(coordinate),
+            None => {
+                self.bounds = Some(Bounds {
+                    min: coordinate.clone(),
+                    max: coordinate.clone(),
+                })
+            }
+        }
+    }
+
+    /// Puts an entity on the map, moving it if it was already placed elsewhere.  The
+    /// given properties apply to the tile for as long as the entity stays there.
+    pub fn place(&mut self, coordinate: &Position, entity: Entity, properties: TileProperties) {
+        self.place_with_opacity(coordinate, entity, properties, 0.);
+    }
+
+    /// Like place, but the entity also partly blocks sight through its tile.
+    /// Opacities of everything on a tile stack, so two entities that each
+    /// block half the light together block three quarters of it.
+    pub fn place_with_opacity(
+        &mut self,
+        coordinate: &Position,
+        entity: Entity,
+        properties: TileProperties,
+        opacity: f32,
+    ) {
+        self.remove_entity(entity.id());
+
+        self.add(coordinate, entity);
+
+        let id = entity.id() as usize;
 
-        entry.insert(entity);
+        if self.placements.len() <= id {
+            self.placements.resize(id + 1, None);
+        }
+
+        self.placements[id] = Some(Placement {
+            entity,
+            coordinate: coordinate.clone(),
+            properties,
+            opacity: opacity.clamp(0., 1.),
+        });
+
+        self.refresh_tile(coordinate);
+    }
+
+    /// Takes a placed entity back off the map.  Only needs the index so that it
+    /// can be used after the entity itself has been deleted.
+    pub fn remove_entity(&mut self, id: Index) {
+        let placement = match self.placements.get_mut(id as usize).and_then(Option::take) {
+            Some(placement) => placement,
+            None => return,
+        };
+
+        let (chunk, i) = chunk_index(&placement.coordinate);
+
+        if let Some(chunk) = self.chunks.get_mut(&chunk) {
+            chunk.entities[i].remove(&placement.entity);
+        }
+
+        self.refresh_tile(&placement.coordinate);
+    }
+
+    fn refresh_tile(&mut self, coordinate: &Position) {
+        let (chunk, i) = chunk_index(coordinate);
+
+        let chunk = match self.chunks.get_mut(&chunk) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+
+        let placements = &self.placements;
+        let mut properties = chunk.marked[i];
+        let mut transparency = 1.;
+
+        for placement in chunk.entities[i]
+            .iter()
+            .filter_map(|entity| placements.get(entity.id() as usize))
+            .flatten()
+            .filter(|placement| placement.coordinate == *coordinate)
+        {
+            properties |= placement.properties;
+            transparency *= 1. - placement.opacity;
+        }
+
+        chunk.properties[i] = properties;
+        chunk.opacity[i] = 1. - transparency;
+    }
+
+    /// The smallest rectangle containing every tile that has had an entity added,
+    /// or None if the map is empty
+    pub fn bounds(&self) -> Option<&Bounds> {
+        self.bounds.as_ref()
     }
 
     pub fn get_entities(&self, coordinate: &Position) -> Option<&HashSet<Entity>> {
-        match self.data.get(coordinate) {
-            None => None,
-            Some(entry) => Some(&entry.entities),
+        let (chunk, i) = chunk_index(coordinate);
+
+        match self.chunks.get(&chunk) {
+            Some(chunk) if !chunk.entities[i].is_empty() => Some(&chunk.entities[i]),
+            _ => None,
         }
     }
 
     pub fn mark_tile(&mut self, coordinate: &Position, flags: TileProperties) {
-        let mut entry = &mut self.data.entry(coordinate.clone()).or_default();
+        let (chunk, i) = self.chunk_mut(coordinate);
 
-        entry.properties = entry.properties | flags;
+        chunk.marked[i] |= flags;
+        chunk.properties[i] |= flags;
     }
 
+    /// Clears anything set with mark_tile, properties from placed entities remain
     pub fn clear_tile_properties(&mut self, coordinate: &Position) {
-        match self.data.get_mut(&coordinate) {
-            None => (),
-            Some(entry) => {
-                entry.properties = TileProperties::empty();
-            }
+        let (chunk, i) = chunk_index(coordinate);
+
+        if let Some(chunk) = self.chunks.get_mut(&chunk) {
+            chunk.marked[i] = TileProperties::empty();
         }
+
+        self.refresh_tile(coordinate);
     }
 
     pub fn tile_is(&self, coordinate: &Position, flags: TileProperties) -> bool {
-        match self.data.get(&coordinate) {
+        let (chunk, i) = chunk_index(coordinate);
+
+        match self.chunks.get(&chunk) {
             None => false,
-            Some(entry) => entry.properties.contains(flags),
+            Some(chunk) => chunk.properties[i].contains(flags),
+        }
+    }
+
+    /// How much of the light passing through the tile gets blocked, where
+    /// anything marked OPAQUE blocks all of it
+    pub fn opacity(&self, coordinate: &Position) -> f32 {
+        let (chunk, i) = chunk_index(coordinate);
+
+        match self.chunks.get(&chunk) {
+            None => 0.,
+            Some(chunk) if chunk.properties[i].contains(TileProperties::OPAQUE) => 1.,
+            Some(chunk) => chunk.opacity[i],
         }
     }
 
     pub fn clear_all(&mut self) {
-        self.data.clear();
+        self.chunks.clear();
+        self.placements.clear();
+        self.bounds = None;
     }
 }
 
@@ -235,6 +423,63 @@ mod tests {
         };
     }
 
+    #[test]
+    fn bounds_cover_all_added_entities() {
+        let mut map: GameMap = GameMap::new();
+
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+
+        assert!(map.bounds().is_none());
+
+        map.add(&Position::new(-3, 4), entity);
+        map.add(&Position::new(5, -2), entity);
+        map.add(&Position::new(1, 1), entity);
+
+        let bounds = map
+            .bounds()
+            .expect("Should have bounds after adding entities");
+
+        assert_eq!(bounds.min, Position::new(-3, -2));
+        assert_eq!(bounds.max, Position::new(5, 4));
+
+        map.clear_all();
+
+        assert!(map.bounds().is_none());
+    }
+
+    #[test]
+    fn tiles_on_chunk_edges_are_independent() {
+        let mut map: GameMap = GameMap::new();
+
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+
+        // Either side of the boundaries between chunks, including negative ones
+        let squares = [
+            Position::new(-1, -1),
+            Position::new(0, 0),
+            Position::new(CHUNK_SIZE - 1, 0),
+            Position::new(CHUNK_SIZE, 0),
+            Position::new(-CHUNK_SIZE, -1),
+            Position::new(-CHUNK_SIZE - 1, -1),
+        ];
+
+        for square in &squares {
+            assert!(!map.tile_is(square, TileProperties::BLOCKED));
+
+            map.mark_tile(square, TileProperties::BLOCKED);
+            map.add(square, entity);
+        }
+
+        for square in &squares {
+            assert!(map.tile_is(square, TileProperties::BLOCKED));
+            assert_eq!(map.get_entities(square).unwrap().len(), 1);
+            assert!(!map.tile_is(&square.down(), TileProperties::BLOCKED));
+            assert!(map.get_entities(&square.down()).is_none());
+        }
+    }
+
     #[test]
     fn marks_square_as_blocked() {
         let mut map: GameMap = GameMap::new();
@@ -245,6 +490,48 @@ mod tests {
         assert!(map.tile_is(&square, TileProperties::BLOCKED));
     }
 
+    #[test]
+    fn placed_entities_set_tile_properties_until_moved() {
+        let mut map: GameMap = GameMap::new();
+
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let first_square = Position { x: -3, y: 4 };
+        let second_square = first_square.up();
+
+        map.place(&first_square, entity, TileProperties::BLOCKED);
+
+        assert!(map.tile_is(&first_square, TileProperties::BLOCKED));
+        assert!(map.get_entities(&first_square).unwrap().contains(&entity));
+
+        map.place(&second_square, entity, TileProperties::BLOCKED);
+
+        assert!(!map.tile_is(&first_square, TileProperties::BLOCKED));
+        assert!(map.tile_is(&second_square, TileProperties::BLOCKED));
+        assert!(map.get_entities(&first_square).is_none());
+        assert!(map.get_entities(&second_square).unwrap().contains(&entity));
+    }
+
+    #[test]
+    fn tile_stays_blocked_while_any_blocking_entity_remains() {
+        let mut map: GameMap = GameMap::new();
+
+        let mut world = World::new();
+        let first_value = world.create_entity().build();
+        let second_value = world.create_entity().build();
+        let square = Position { x: -3, y: 4 };
+
+        map.place(&square, first_value, TileProperties::BLOCKED);
+        map.place(&square, second_value, TileProperties::BLOCKED);
+
+        map.remove_entity(first_value.id());
+        assert!(map.tile_is(&square, TileProperties::BLOCKED));
+
+        map.remove_entity(second_value.id());
+        assert!(!map.tile_is(&square, TileProperties::BLOCKED));
+        assert!(map.get_entities(&square).is_none());
+    }
+
     #[test]
     fn clears_tile_properties() {
         let mut map: GameMap = GameMap::new();
@@ -255,4 +542,28 @@ mod tests {
         map.clear_tile_properties(&square);
         assert!(!map.tile_is(&square, TileProperties::BLOCKED));
     }
+
+    #[test]
+    fn opacity_stacks_and_follows_entities() {
+        let mut map: GameMap = GameMap::new();
+
+        let mut world = World::new();
+        let first_value = world.create_entity().build();
+        let second_value = world.create_entity().build();
+        let square = Position { x: -3, y: 4 };
+
+        map.place_with_opacity(&square, first_value, TileProperties::empty(), 0.5);
+        map.place_with_opacity(&square, second_value, TileProperties::empty(), 0.5);
+
+        assert_eq!(map.opacity(&square), 0.75);
+
+        map.place(&square.down(), first_value, TileProperties::empty());
+
+        assert_eq!(map.opacity(&square), 0.5);
+        assert_eq!(map.opacity(&square.down()), 0.);
+
+        map.mark_tile(&square, TileProperties::OPAQUE);
+
+        assert_eq!(map.opacity(&square), 1.);
+    }
 }
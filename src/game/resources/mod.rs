@@ -1,17 +1,162 @@
+pub mod game_log;
 pub mod game_map;
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Default)]
 pub struct DeltaTime(pub std::time::Duration);
 
-#[derive(Default)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct CameraCenter {
     pub x: i32,
     pub y: i32,
 }
 
+/// How the camera follows the player around the map
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CameraMode {
+    /// Keep the player exactly in the center of the viewport
+    #[default]
+    Center,
+
+    /// Only move the camera once the player leaves a rectangle of the given
+    /// size around the camera center
+    Deadzone { width: i32, height: i32 },
+
+    /// Recenter on the player once they come within `margin` tiles of the edge
+    /// of the viewport
+    EdgeScroll { margin: i32 },
+}
+
+/// The size in tiles of the area the map is drawn into, as of the last render
+#[derive(Default)]
+pub struct ViewportSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Dijkstra maps leading toward and away from the player, shared by anything
+/// that wants to chase or run.  Recomputed whenever the player moves.
+#[derive(Default)]
+pub struct PlayerFlowField {
+    pub toward: super::pathfinding::DijkstraMap,
+    pub away: super::pathfinding::DijkstraMap,
+
+    /// Where the player was when the maps were built
+    pub origin: Option<super::components::Position>,
+}
+
+/// Amount of red, green and blue light falling on a tile, where 1 is fully lit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Light {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Light {
+    pub fn brightness(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+}
+
+/// Tiles dimmer than this are too dark to make anything out
+pub const MIN_VISIBLE_LIGHT: f32 = 0.05;
+
+/// How much light reaches each tile, rebuilt by the lighting pass
+#[derive(Default)]
+pub struct LightMap {
+    pub tiles: std::collections::HashMap<super::components::Position, Light>,
+}
+
+impl LightMap {
+    pub fn light_at(&self, pos: &super::components::Position) -> Option<&Light> {
+        self.tiles.get(pos)
+    }
+
+    pub fn is_lit(&self, pos: &super::components::Position) -> bool {
+        self.light_at(pos)
+            .is_some_and(|light| light.brightness() >= MIN_VISIBLE_LIGHT)
+    }
+}
+
+/// How much time has passed in the game world, in time units
+#[derive(Default)]
+pub struct GameClock {
+    pub time: u64,
+
+    /// Whether a time unit passed during the current dispatch, for anything
+    /// that changes on its own as time goes by
+    pub ticked: bool,
+}
+
+/// Who gets to act during the current dispatch, as decided by the scheduler
+#[derive(Default)]
+pub struct Turn {
+    /// Ready computer controlled actors, most energetic first
+    pub ready: Vec<specs::Entity>,
+
+    /// Nothing happens until the player acts
+    pub player_ready: bool,
+}
+
+/// Shared source of randomness for anything that happens during play, seeded so
+/// that a game can be replayed
+pub struct GameRng(pub rand_pcg::Pcg32);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        use rand::SeedableRng;
+
+        GameRng(rand_pcg::Pcg32::seed_from_u64(0))
+    }
+}
+
 pub struct Player {
     pub ent: specs::Entity,
 }
 
+/// Something getting hurt, waiting for the damage system to apply it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DamageEvent {
+    pub target: specs::Entity,
+
+    /// Whoever did it, if it was anyone at all
+    pub source: Option<specs::Entity>,
+
+    pub amount: i32,
+}
+
+/// All the damage done during the current dispatch
+#[derive(Default)]
+pub struct DamageEvents {
+    pub pending: Vec<DamageEvent>,
+}
+
+/// Whether the player has their inventory open, and which of the items they
+/// carry is picked out
+#[derive(Default)]
+pub struct InventoryScreen {
+    pub open: bool,
+    pub selected: usize,
+}
+
+impl InventoryScreen {
+    pub fn select_next(&mut self, len: usize) {
+        if len > 0 {
+            self.selected = (self.selected + 1).min(len - 1);
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Keeps the selection on an item after some have gone
+    pub fn clamp(&mut self, len: usize) {
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+}
+
 #[derive(Default)]
 pub struct PendingAction(pub Option<super::input::Action>);
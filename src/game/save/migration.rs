@@ -0,0 +1,131 @@
+use super::{Error, VERSION};
+use serde_json::Value;
+use std::convert::TryFrom;
+
+/// Upgrades a save file from one version to the next, working on the raw JSON
+/// since the types it was written from are gone
+pub type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// Migrations from each version to the one after it, starting at version 1.
+/// Version `VERSION` needs `VERSION - 1` of them.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Brings a save file up to the current version
+pub fn upgrade(value: Value) -> Result<Value, Error> {
+    upgrade_with(value, VERSION, MIGRATIONS)
+}
+
+fn upgrade_with(mut value: Value, current: u32, migrations: &[Migration]) -> Result<Value, Error> {
+    let saved = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(Error::MissingVersion)?;
+
+    let mut version = u32::try_from(saved)
+        .ok()
+        .filter(|version| *version <= current)
+        .ok_or(Error::TooNew(saved))?;
+
+    while version < current {
+        let migrate = version
+            .checked_sub(1)
+            .and_then(|i| migrations.get(i as usize))
+            .ok_or_else(|| Error::Migration {
+                from: version,
+                reason: String::from("no migration from this version"),
+            })?;
+
+        migrate(&mut value).map_err(|reason| Error::Migration {
+            from: version,
+            reason,
+        })?;
+
+        version += 1;
+        value["version"] = Value::from(version);
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_hp(value: &mut Value) -> Result<(), String> {
+        for entity in value["entities"].as_array_mut().ok_or("no entities")? {
+            if let Some(hp) = entity.as_object_mut().and_then(|e| e.remove("hp")) {
+                entity["health"] = json!({ "current": hp, "max": hp });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_time(value: &mut Value) -> Result<(), String> {
+        value["time"] = json!(0);
+
+        Ok(())
+    }
+
+    fn fail(_: &mut Value) -> Result<(), String> {
+        Err(String::from("broken"))
+    }
+
+    #[test]
+    fn applies_every_migration_in_order() {
+        let old = json!({ "version": 1, "entities": [{ "hp": 5 }] });
+
+        let upgraded = upgrade_with(old, 3, &[rename_hp, add_time]).unwrap();
+
+        assert_eq!(
+            upgraded,
+            json!({
+                "version": 3,
+                "time": 0,
+                "entities": [{ "health": { "current": 5, "max": 5 } }],
+            })
+        );
+    }
+
+    #[test]
+    fn starts_from_the_saved_version() {
+        let old = json!({ "version": 2, "entities": [{ "hp": 5 }] });
+
+        let upgraded = upgrade_with(old, 3, &[fail, add_time]).unwrap();
+
+        assert_eq!(upgraded["version"], json!(3));
+        assert_eq!(upgraded["entities"][0]["hp"], json!(5));
+    }
+
+    #[test]
+    fn leaves_current_saves_alone() {
+        let save = json!({ "version": 3, "entities": [] });
+
+        assert_eq!(upgrade_with(save.clone(), 3, &[fail, fail]).unwrap(), save);
+    }
+
+    #[test]
+    fn rejects_saves_it_cannot_upgrade() {
+        assert!(matches!(
+            upgrade_with(json!({ "version": 4 }), 3, &[]),
+            Err(Error::TooNew(4))
+        ));
+        assert!(matches!(
+            upgrade_with(json!({ "version": 4294967297u64 }), 3, &[]),
+            Err(Error::TooNew(4294967297))
+        ));
+        assert!(matches!(
+            upgrade_with(json!({ "entities": [] }), 3, &[]),
+            Err(Error::MissingVersion)
+        ));
+        assert!(matches!(
+            upgrade_with(json!({ "version": 1 }), 3, &[add_time, fail]),
+            Err(Error::Migration { from: 2, .. })
+        ));
+        assert!(matches!(
+            upgrade_with(json!({ "version": 1 }), 2, &[]),
+            Err(Error::Migration { from: 1, .. })
+        ));
+    }
+}
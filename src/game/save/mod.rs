@@ -0,0 +1,708 @@
+use super::{
+    components::{self, equipment, fluid, material},
+    resources::{self, game_log::GameLog},
+};
+use serde::{Deserialize, Serialize};
+use specs::{Builder, Component, Entity, Join, World, WorldExt};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+pub mod migration;
+
+/*
+Save files are JSON holding a format version, a few resources and a list of
+every entity with the components worth keeping.  Entities refer to each other
+by their index in that list, so that they can be recreated with whatever ids
+the new world hands out.  Anything worked out from other state, such as the
+game map, light map and what's currently visible, is rebuilt by the systems
+instead of being saved.
+*/
+
+/// Bumped whenever the layout of save files changes, along with adding a
+/// migration from the version before
+pub const VERSION: u32 = 1;
+
+/// Where an entity is in the save file's list of entities
+pub type SaveId = u32;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    MissingVersion,
+
+    /// Written by a newer version of the game than this one
+    TooNew(u64),
+
+    Migration {
+        from: u32,
+        reason: String,
+    },
+
+    /// Something in the save refers to an entity that isn't in it
+    UnknownEntity(SaveId),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Format(e) => write!(f, "invalid save file: {}", e),
+            Error::MissingVersion => write!(f, "save file has no version"),
+            Error::TooNew(version) => write!(
+                f,
+                "save file version {} is newer than this game understands ({})",
+                version, VERSION
+            ),
+            Error::Migration { from, reason } => {
+                write!(
+                    f,
+                    "can't upgrade save file from version {}: {}",
+                    from, reason
+                )
+            }
+            Error::UnknownEntity(id) => write!(f, "save file refers to missing entity {}", id),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Format(e)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SavedInventory {
+    pub items: Vec<SaveId>,
+    pub capacity: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SavedEquipment {
+    pub weapon: Option<SaveId>,
+    pub armor: Option<SaveId>,
+    pub light: Option<SaveId>,
+}
+
+/// Everything kept about a single entity.  Components it doesn't have are left
+/// out of the file entirely.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SavedEntity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<components::Position>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<components::Sprite>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<material::Material>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<components::Shape>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub player: bool,
+
+    /// Only the radius, what's in view gets worked out again after loading
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub viewshed_radius: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explored: Option<components::Explored>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_source: Option<components::LightSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burning: Option<components::Burning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy: Option<components::Energy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ai: Option<components::Ai>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<components::Name>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faction: Option<components::Faction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<components::Health>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub leaves_corpse: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attack: Option<components::Attack>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defense: Option<components::Defense>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fluid: Option<fluid::Fluid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<components::Item>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equippable: Option<equipment::Equippable>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<SavedInventory>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carried_by: Option<SaveId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equipment: Option<SavedEquipment>,
+}
+
+impl SavedEntity {
+    /// Every other entity this one refers to
+    fn references(&self) -> Vec<SaveId> {
+        let mut references = Vec::new();
+
+        if let Some(inventory) = &self.inventory {
+            references.extend(&inventory.items);
+        }
+
+        if let Some(equipment) = &self.equipment {
+            references.extend(equipment.weapon);
+            references.extend(equipment.armor);
+            references.extend(equipment.light);
+        }
+
+        references.extend(self.carried_by);
+
+        references
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub player: SaveId,
+    pub camera_center: resources::CameraCenter,
+    pub camera_mode: resources::CameraMode,
+    pub time: u64,
+    pub rng: rand_pcg::Pcg32,
+    pub log: GameLog,
+    pub entities: Vec<SavedEntity>,
+}
+
+impl SaveFile {
+    /// Checks that everything the save refers to is actually in it
+    pub fn validate(&self) -> Result<(), Error> {
+        let count = self.entities.len() as SaveId;
+
+        let unknown = std::iter::once(self.player)
+            .chain(self.entities.iter().flat_map(SavedEntity::references))
+            .find(|id| *id >= count);
+
+        match unknown {
+            Some(id) => Err(Error::UnknownEntity(id)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Takes down everything worth keeping about the world.  Every component that
+/// gets saved needs to be registered.
+pub fn snapshot(world: &World) -> SaveFile {
+    let live: Vec<Entity> = (&world.entities()).join().collect();
+    let ids: HashMap<Entity, SaveId> = live
+        .iter()
+        .enumerate()
+        .map(|(i, entity)| (*entity, i as SaveId))
+        .collect();
+
+    // Anything referring to an entity that's gone refers to nothing instead
+    let id = |entity: Option<Entity>| entity.and_then(|entity| ids.get(&entity).copied());
+
+    let positions = world.read_storage::<components::Position>();
+    let sprites = world.read_storage::<components::Sprite>();
+    let materials = world.read_storage::<material::Material>();
+    let shapes = world.read_storage::<components::Shape>();
+    let players = world.read_storage::<components::Player>();
+    let viewsheds = world.read_storage::<components::Viewshed>();
+    let explored = world.read_storage::<components::Explored>();
+    let lights = world.read_storage::<components::LightSource>();
+    let burning = world.read_storage::<components::Burning>();
+    let energy = world.read_storage::<components::Energy>();
+    let ais = world.read_storage::<components::Ai>();
+    let names = world.read_storage::<components::Name>();
+    let factions = world.read_storage::<components::Faction>();
+    let healths = world.read_storage::<components::Health>();
+    let corpses = world.read_storage::<components::LeavesCorpse>();
+    let attacks = world.read_storage::<components::Attack>();
+    let defenses = world.read_storage::<components::Defense>();
+    let fluids = world.read_storage::<fluid::Fluid>();
+    let items = world.read_storage::<components::Item>();
+    let equippables = world.read_storage::<equipment::Equippable>();
+    let inventories = world.read_storage::<components::Inventory>();
+    let carried = world.read_storage::<components::Carried>();
+    let equipment = world.read_storage::<equipment::Equipment>();
+
+    let entities = live
+        .iter()
+        .map(|entity| SavedEntity {
+            position: positions.get(*entity).cloned(),
+            sprite: sprites.get(*entity).cloned(),
+            material: materials.get(*entity).cloned(),
+            shape: shapes.get(*entity).cloned(),
+            player: players.contains(*entity),
+            viewshed_radius: viewsheds.get(*entity).map(|viewshed| viewshed.radius),
+            explored: explored.get(*entity).cloned(),
+            light_source: lights.get(*entity).cloned(),
+            burning: burning.get(*entity).cloned(),
+            energy: energy.get(*entity).cloned(),
+            ai: ais.get(*entity).cloned(),
+            name: names.get(*entity).cloned(),
+            faction: factions.get(*entity).cloned(),
+            health: healths.get(*entity).cloned(),
+            leaves_corpse: corpses.contains(*entity),
+            attack: attacks.get(*entity).cloned(),
+            defense: defenses.get(*entity).cloned(),
+            fluid: fluids.get(*entity).cloned(),
+            item: items.get(*entity).cloned(),
+            equippable: equippables.get(*entity).cloned(),
+            inventory: inventories.get(*entity).map(|inventory| SavedInventory {
+                items: inventory
+                    .items
+                    .iter()
+                    .filter_map(|item| id(Some(*item)))
+                    .collect(),
+                capacity: inventory.capacity,
+            }),
+            carried_by: carried
+                .get(*entity)
+                .and_then(|carried| id(Some(carried.by))),
+            equipment: equipment.get(*entity).map(|equipment| SavedEquipment {
+                weapon: id(equipment.weapon),
+                armor: id(equipment.armor),
+                light: id(equipment.light),
+            }),
+        })
+        .collect();
+
+    let clock = world.read_resource::<resources::GameClock>();
+    let camera_center = world.read_resource::<resources::CameraCenter>();
+
+    SaveFile {
+        version: VERSION,
+        player: id(Some(world.read_resource::<resources::Player>().ent)).unwrap_or(0),
+        camera_center: resources::CameraCenter {
+            x: camera_center.x,
+            y: camera_center.y,
+        },
+        camera_mode: (*world.read_resource::<resources::CameraMode>()).clone(),
+        time: clock.time,
+        rng: world.read_resource::<resources::GameRng>().0.clone(),
+        log: (*world.read_resource::<GameLog>()).clone(),
+        entities,
+    }
+}
+
+fn insert<T: Component>(world: &World, entity: Entity, component: Option<T>) {
+    if let Some(component) = component {
+        world
+            .write_storage::<T>()
+            .insert(entity, component)
+            .expect("Restored entity should be alive");
+    }
+}
+
+/// Recreates a saved world.  This is meant for a world with everything
+/// registered but no entities yet, and leaves it alone if the save turns out
+/// not to make sense.
+pub fn restore(world: &mut World, save: SaveFile) -> Result<(), Error> {
+    save.validate()?;
+
+    let created: Vec<Entity> = save
+        .entities
+        .iter()
+        .map(|_| world.create_entity().build())
+        .collect();
+
+    let entity = |id: SaveId| created[id as usize];
+
+    for (ent, saved) in created.iter().copied().zip(save.entities) {
+        insert(world, ent, saved.position);
+        insert(world, ent, saved.sprite);
+        insert(world, ent, saved.material);
+        insert(world, ent, saved.shape);
+        if saved.player {
+            insert(world, ent, Some(components::Player));
+        }
+
+        insert(
+            world,
+            ent,
+            saved.viewshed_radius.map(components::Viewshed::new),
+        );
+        insert(world, ent, saved.explored);
+        insert(world, ent, saved.light_source);
+        insert(world, ent, saved.burning);
+        insert(world, ent, saved.energy);
+        insert(world, ent, saved.ai);
+        insert(world, ent, saved.name);
+        insert(world, ent, saved.faction);
+        insert(world, ent, saved.health);
+
+        if saved.leaves_corpse {
+            insert(world, ent, Some(components::LeavesCorpse));
+        }
+
+        insert(world, ent, saved.attack);
+        insert(world, ent, saved.defense);
+        insert(world, ent, saved.fluid);
+        insert(world, ent, saved.item);
+        insert(world, ent, saved.equippable);
+
+        insert(
+            world,
+            ent,
+            saved.inventory.map(|inventory| components::Inventory {
+                items: inventory.items.into_iter().map(entity).collect(),
+                capacity: inventory.capacity,
+            }),
+        );
+        insert(
+            world,
+            ent,
+            saved
+                .carried_by
+                .map(|by| components::Carried { by: entity(by) }),
+        );
+        insert(
+            world,
+            ent,
+            saved.equipment.map(|equipment| equipment::Equipment {
+                weapon: equipment.weapon.map(entity),
+                armor: equipment.armor.map(entity),
+                light: equipment.light.map(entity),
+            }),
+        );
+    }
+
+    world.insert(resources::Player {
+        ent: entity(save.player),
+    });
+    world.insert(save.camera_center);
+    world.insert(save.camera_mode);
+    world.insert(resources::GameClock {
+        time: save.time,
+        ticked: false,
+    });
+    world.insert(resources::GameRng(save.rng));
+    world.insert(save.log);
+
+    Ok(())
+}
+
+/// Saves the world to a file.  It's written next to the old save first, so
+/// that failing halfway doesn't lose that one too.
+pub fn write(world: &World, path: &Path) -> Result<(), Error> {
+    let json = serde_json::to_string(&snapshot(world))?;
+    let partial = path.with_extension("partial");
+
+    std::fs::write(&partial, json)?;
+    std::fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+/// The path if there's nothing there yet, or else the first free one numbered
+/// after it, such as `adventure-2.save` for `adventure.save`
+pub fn unused_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    std::iter::once(path.to_path_buf())
+        .chain((2..).map(|n| path.with_file_name(format!("{}-{}{}", stem, n, extension))))
+        .find(|candidate| !candidate.exists())
+        .expect("There are always more numbers to try")
+}
+
+/// Reads a save file, upgrading it first if it's from an older version
+pub fn read(path: &Path) -> Result<SaveFile, Error> {
+    let text = std::fs::read_to_string(path)?;
+    let value = migration::upgrade(serde_json::from_str(&text)?)?;
+
+    let save: SaveFile = serde_json::from_value(value)?;
+
+    save.validate()?;
+
+    Ok(save)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::Sprite>();
+        world.register::<material::Material>();
+        world.register::<components::Shape>();
+        world.register::<components::Player>();
+        world.register::<components::Viewshed>();
+        world.register::<components::Explored>();
+        world.register::<components::LightSource>();
+        world.register::<components::Burning>();
+        world.register::<components::Energy>();
+        world.register::<components::Ai>();
+        world.register::<components::Name>();
+        world.register::<components::Faction>();
+        world.register::<components::Health>();
+        world.register::<components::LeavesCorpse>();
+        world.register::<components::Attack>();
+        world.register::<components::Defense>();
+        world.register::<fluid::Fluid>();
+        world.register::<components::Item>();
+        world.register::<equipment::Equippable>();
+        world.register::<components::Inventory>();
+        world.register::<components::Carried>();
+        world.register::<equipment::Equipment>();
+
+        world.insert(resources::CameraCenter { x: 0, y: 0 });
+        world.insert(resources::CameraMode::default());
+        world.insert(resources::GameClock::default());
+        world.insert(resources::GameRng::default());
+        world.insert(GameLog::default());
+
+        world
+    }
+
+    /// A player carrying and wielding a sword, with a wall nearby
+    fn populate(world: &mut World) -> (Entity, Entity) {
+        world
+            .create_entity()
+            .with(components::Position::new(1, 0))
+            .with(material::stone())
+            .with(components::Shape::FullBlock)
+            .build();
+
+        let sword = world
+            .create_entity()
+            .with(components::Name {
+                name: String::from("sword"),
+            })
+            .with(components::Item { effect: None })
+            .with(equipment::Equippable {
+                slot: equipment::Slot::Weapon,
+                attack: 4,
+                vision: 0,
+            })
+            .build();
+
+        let mut explored = components::Explored::default();
+
+        explored.tiles.insert(components::Position::new(1, 0));
+
+        let player = world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::Player)
+            .with(components::LeavesCorpse)
+            .with(components::Viewshed::new(7))
+            .with(explored)
+            .with(components::Health {
+                current: 12,
+                max: 30,
+            })
+            .with(components::Inventory {
+                items: vec![sword],
+                capacity: 26,
+            })
+            .with(equipment::Equipment {
+                weapon: Some(sword),
+                ..Default::default()
+            })
+            .build();
+
+        world
+            .write_storage::<components::Carried>()
+            .insert(sword, components::Carried { by: player })
+            .unwrap();
+        world.insert(resources::Player { ent: player });
+
+        (player, sword)
+    }
+
+    /// Saves and loads through JSON, the way a save file would
+    fn round_trip(world: &World) -> World {
+        let json = serde_json::to_string(&snapshot(world)).unwrap();
+        let save = serde_json::from_str(&json).unwrap();
+        let mut loaded = build_world();
+
+        restore(&mut loaded, save).unwrap();
+
+        loaded
+    }
+
+    #[test]
+    fn restores_components_and_references_between_entities() {
+        let mut world = build_world();
+
+        // Leave a gap in the entity ids, so they can't just line up by luck
+        let gone = world.create_entity().build();
+
+        populate(&mut world);
+        world.delete_entity(gone).unwrap();
+        world.maintain();
+
+        let loaded = round_trip(&world);
+        let player = loaded.read_resource::<resources::Player>().ent;
+
+        assert!(loaded.read_storage::<components::Player>().contains(player));
+        assert!(loaded
+            .read_storage::<components::LeavesCorpse>()
+            .contains(player));
+        assert_eq!(
+            loaded.read_storage::<components::Health>().get(player),
+            Some(&components::Health {
+                current: 12,
+                max: 30
+            })
+        );
+        assert_eq!(
+            loaded
+                .read_storage::<components::Viewshed>()
+                .get(player)
+                .unwrap()
+                .radius,
+            7
+        );
+        assert!(loaded
+            .read_storage::<components::Explored>()
+            .get(player)
+            .unwrap()
+            .tiles
+            .contains(&components::Position::new(1, 0)));
+
+        let sword = loaded
+            .read_storage::<components::Inventory>()
+            .get(player)
+            .unwrap()
+            .items[0];
+
+        assert_eq!(
+            loaded
+                .read_storage::<components::Name>()
+                .get(sword)
+                .unwrap()
+                .name,
+            "sword"
+        );
+        assert_eq!(
+            loaded
+                .read_storage::<equipment::Equipment>()
+                .get(player)
+                .unwrap()
+                .weapon,
+            Some(sword)
+        );
+        assert_eq!(
+            loaded.read_storage::<components::Carried>().get(sword),
+            Some(&components::Carried { by: player })
+        );
+        assert_eq!(
+            (&loaded.read_storage::<components::Position>())
+                .join()
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn carries_on_with_the_same_random_numbers() {
+        use rand::Rng;
+
+        let mut world = build_world();
+
+        populate(&mut world);
+        world.write_resource::<resources::GameRng>().0.gen::<u32>();
+
+        let loaded = round_trip(&world);
+
+        let expected: u32 = world.write_resource::<resources::GameRng>().0.gen();
+        let actual: u32 = loaded.write_resource::<resources::GameRng>().0.gen();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn keeps_the_message_log_and_clock() {
+        let mut world = build_world();
+
+        populate(&mut world);
+        world
+            .write_resource::<GameLog>()
+            .push(String::from("Hello"), resources::game_log::WHITE);
+        world.write_resource::<resources::GameClock>().time = 1234;
+
+        let loaded = round_trip(&world);
+
+        assert_eq!(loaded.read_resource::<resources::GameClock>().time, 1234);
+        assert_eq!(
+            loaded.read_resource::<GameLog>().visible(1)[0].text,
+            "Hello"
+        );
+    }
+
+    #[test]
+    fn refuses_saves_referring_to_missing_entities() {
+        let mut world = build_world();
+
+        populate(&mut world);
+
+        let mut save = snapshot(&world);
+
+        save.entities.truncate(1);
+
+        let mut loaded = build_world();
+
+        assert!(matches!(
+            restore(&mut loaded, save),
+            Err(Error::UnknownEntity(_))
+        ));
+
+        // Nothing gets half loaded
+        assert_eq!((&loaded.entities()).join().count(), 0);
+    }
+
+    #[test]
+    fn writes_and_reads_save_files() {
+        let mut world = build_world();
+
+        populate(&mut world);
+
+        let path = std::env::temp_dir().join(format!("adventure-save-{}.json", std::process::id()));
+
+        write(&world, &path).unwrap();
+        let save = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(save.version, VERSION);
+        assert_eq!(save.entities.len(), 3);
+    }
+
+    #[test]
+    fn finds_a_path_that_is_not_taken() {
+        let dir = std::env::temp_dir().join(format!("adventure-saves-{}", std::process::id()));
+        let path = dir.join("adventure.save");
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(unused_path(&path), path);
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(unused_path(&path), dir.join("adventure-2.save"));
+
+        std::fs::write(dir.join("adventure-2.save"), "").unwrap();
+        assert_eq!(unused_path(&path), dir.join("adventure-3.save"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
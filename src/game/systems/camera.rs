@@ -0,0 +1,240 @@
+use super::super::{components, resources, resources::game_map::GameMap};
+use specs::{Read, ReadStorage, System, Write};
+
+pub struct Camera;
+
+fn follow_deadzone(camera: i32, target: i32, extent: i32) -> i32 {
+    let half = extent / 2;
+
+    if target > camera + half {
+        target - half
+    } else if target < camera - half {
+        target + half
+    } else {
+        camera
+    }
+}
+
+fn follow_edge(camera: i32, target: i32, viewport: i32, margin: i32) -> i32 {
+    if viewport <= 0 {
+        return target;
+    }
+
+    let low = camera - viewport / 2;
+    let high = low + viewport - 1;
+
+    if target < low + margin || target > high - margin {
+        target
+    } else {
+        camera
+    }
+}
+
+fn clamp_axis(camera: i32, viewport: i32, map_min: i32, map_max: i32) -> i32 {
+    if viewport <= 0 {
+        return camera;
+    }
+
+    let map_size = map_max - map_min + 1;
+
+    // If the whole map fits then just keep it centered
+    if map_size <= viewport {
+        return (map_min + map_max).div_euclid(2);
+    }
+
+    let half = viewport / 2;
+
+    camera.clamp(map_min + half, map_max - (viewport - 1 - half))
+}
+
+impl<'a> System<'a> for Camera {
+    type SystemData = (
+        Option<Read<'a, resources::Player>>,
+        ReadStorage<'a, components::Position>,
+        Read<'a, resources::CameraMode>,
+        Read<'a, resources::ViewportSize>,
+        Read<'a, GameMap>,
+        Write<'a, resources::CameraCenter>,
+    );
+
+    fn run(
+        &mut self,
+        (player, positions, mode, viewport, game_map, mut camera_center): Self::SystemData,
+    ) {
+        let pos_player = match player.and_then(|player| positions.get(player.ent)) {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        let width = viewport.width as i32;
+        let height = viewport.height as i32;
+
+        let (mut x, mut y) = match *mode {
+            resources::CameraMode::Center => (pos_player.x, pos_player.y),
+
+            resources::CameraMode::Deadzone {
+                width: deadzone_width,
+                height: deadzone_height,
+            } => (
+                follow_deadzone(camera_center.x, pos_player.x, deadzone_width),
+                follow_deadzone(camera_center.y, pos_player.y, deadzone_height),
+            ),
+
+            resources::CameraMode::EdgeScroll { margin } => (
+                follow_edge(camera_center.x, pos_player.x, width, margin),
+                follow_edge(camera_center.y, pos_player.y, height, margin),
+            ),
+        };
+
+        if let Some(bounds) = game_map.bounds() {
+            x = clamp_axis(x, width, bounds.min.x, bounds.max.x);
+            y = clamp_axis(y, height, bounds.min.y, bounds.max.y);
+        }
+
+        camera_center.x = x;
+        camera_center.y = y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world(
+        mode: resources::CameraMode,
+        camera: components::Position,
+        player: components::Position,
+    ) -> specs::World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+
+        world.insert(mode);
+        world.insert(resources::ViewportSize {
+            width: 20,
+            height: 10,
+        });
+        world.insert(resources::CameraCenter {
+            x: camera.x,
+            y: camera.y,
+        });
+        world.insert(GameMap::new());
+
+        let ent = world.create_entity().with(player).build();
+
+        world.insert(resources::Player { ent });
+
+        world
+    }
+
+    fn run_camera(world: &mut specs::World) -> components::Position {
+        let mut camera = Camera;
+        camera.run_now(world);
+        world.maintain();
+
+        let camera_center = world.read_resource::<resources::CameraCenter>();
+
+        components::Position::new(camera_center.x, camera_center.y)
+    }
+
+    #[test]
+    fn center_mode_follows_player_exactly() {
+        let mut world = build_world(
+            resources::CameraMode::Center,
+            components::Position::new(0, 0),
+            components::Position::new(13, -7),
+        );
+
+        assert_eq!(run_camera(&mut world), components::Position::new(13, -7));
+    }
+
+    #[test]
+    fn deadzone_mode_does_not_move_while_player_inside_deadzone() {
+        let mut world = build_world(
+            resources::CameraMode::Deadzone {
+                width: 6,
+                height: 4,
+            },
+            components::Position::new(0, 0),
+            components::Position::new(3, -2),
+        );
+
+        assert_eq!(run_camera(&mut world), components::Position::new(0, 0));
+    }
+
+    #[test]
+    fn deadzone_mode_drags_camera_when_player_leaves_deadzone() {
+        let mut world = build_world(
+            resources::CameraMode::Deadzone {
+                width: 6,
+                height: 4,
+            },
+            components::Position::new(0, 0),
+            components::Position::new(5, -6),
+        );
+
+        // Player ends up on the edge of the deadzone
+        assert_eq!(run_camera(&mut world), components::Position::new(2, -4));
+    }
+
+    #[test]
+    fn edge_scroll_mode_recenters_when_player_near_edge() {
+        // Viewport is 20 wide, so the visible range is -10..=9
+        let mut world = build_world(
+            resources::CameraMode::EdgeScroll { margin: 2 },
+            components::Position::new(0, 0),
+            components::Position::new(7, 1),
+        );
+
+        assert_eq!(run_camera(&mut world), components::Position::new(0, 0));
+
+        let mut world = build_world(
+            resources::CameraMode::EdgeScroll { margin: 2 },
+            components::Position::new(0, 0),
+            components::Position::new(8, 1),
+        );
+
+        assert_eq!(run_camera(&mut world), components::Position::new(8, 0));
+    }
+
+    #[test]
+    fn clamps_camera_to_map_bounds() {
+        let mut world = build_world(
+            resources::CameraMode::Center,
+            components::Position::new(0, 0),
+            components::Position::new(48, 2),
+        );
+
+        {
+            let mut game_map = world.write_resource::<GameMap>();
+            let ent = world.entities().create();
+
+            game_map.add(&components::Position::new(-50, -50), ent);
+            game_map.add(&components::Position::new(50, 50), ent);
+        }
+
+        // Viewport is 20 wide so the camera can't get closer than 10 to the
+        // left edge and 9 to the right edge without showing outside the map
+        assert_eq!(run_camera(&mut world), components::Position::new(41, 2));
+    }
+
+    #[test]
+    fn centers_map_smaller_than_viewport() {
+        let mut world = build_world(
+            resources::CameraMode::Center,
+            components::Position::new(0, 0),
+            components::Position::new(3, 1),
+        );
+
+        {
+            let mut game_map = world.write_resource::<GameMap>();
+            let ent = world.entities().create();
+
+            game_map.add(&components::Position::new(0, 0), ent);
+            game_map.add(&components::Position::new(4, 2), ent);
+        }
+
+        assert_eq!(run_camera(&mut world), components::Position::new(2, 1));
+    }
+}
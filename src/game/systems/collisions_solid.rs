@@ -3,29 +3,82 @@ use super::super::{
     resources::game_map::{GameMap, TileProperties},
 };
 use specs::{Read, ReadStorage, System, WriteStorage};
+use std::collections::HashSet;
 
+/// Cancels moves that would put something solid where it doesn't fit: into a
+/// blocked tile, or for anything that occupies its tile, into a tile where
+/// another one is staying or that one earlier in entity order already claimed.
+/// Settling moves in a fixed order means the same moves always end the same.
 pub struct CollisionsSolid;
 
 impl<'a> System<'a> for CollisionsSolid {
     type SystemData = (
         WriteStorage<'a, components::Moved>,
         ReadStorage<'a, components::material::Material>,
+        ReadStorage<'a, components::Shape>,
         specs::Entities<'a>,
         Read<'a, GameMap>,
     );
 
-    fn run(&mut self, (mut moved, materials, entities, game_map): Self::SystemData) {
+    fn run(&mut self, (mut moved, materials, shapes, entities, game_map): Self::SystemData) {
         use specs::Join;
 
-        let mut to_remove = Vec::new();
+        let occupies = |entity: specs::Entity| {
+            materials.get(entity).is_some_and(|material| material.solid)
+                && shapes
+                    .get(entity)
+                    .is_some_and(|shape| shape.occupies_tile())
+        };
+
+        let mut cancelled = HashSet::new();
+        let mut occupiers = Vec::new();
 
         for (mv, entity, material) in (&moved, &entities, &materials).join() {
-            if material.solid && game_map.tile_is(&mv.to, TileProperties::BLOCKED) {
-                to_remove.push(entity);
+            if !material.solid {
+                continue;
+            }
+
+            if game_map.tile_is(&mv.to, TileProperties::BLOCKED) {
+                cancelled.insert(entity);
+            } else if occupies(entity) {
+                occupiers.push((entity, mv.to.clone()));
             }
         }
 
-        for entity in to_remove {
+        // Cancelling one move can leave something standing in the way of
+        // another, so keep going until every move left has room
+        loop {
+            let mut claimed = HashSet::new();
+            let mut changed = false;
+
+            for (entity, to) in &occupiers {
+                if cancelled.contains(entity) {
+                    continue;
+                }
+
+                let staying = game_map.tile_is(to, TileProperties::OCCUPIED)
+                    && game_map
+                        .get_entities(to)
+                        .into_iter()
+                        .flatten()
+                        .any(|other| {
+                            other != entity
+                                && occupies(*other)
+                                && (cancelled.contains(other) || !moved.contains(*other))
+                        });
+
+                if staying || !claimed.insert(to.clone()) {
+                    cancelled.insert(*entity);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        for entity in cancelled {
             moved.remove(entity);
         }
     }
@@ -47,6 +100,7 @@ mod tests {
         world.register::<components::Position>();
         world.register::<components::Moved>();
         world.register::<components::material::Material>();
+        world.register::<components::Shape>();
 
         game_map.mark_tile(&target, TileProperties::BLOCKED);
 
@@ -86,6 +140,7 @@ mod tests {
         world.register::<components::Position>();
         world.register::<components::Moved>();
         world.register::<components::material::Material>();
+        world.register::<components::Shape>();
 
         world.insert(game_map);
 
@@ -123,6 +178,7 @@ mod tests {
         world.register::<components::Position>();
         world.register::<components::Moved>();
         world.register::<components::material::Material>();
+        world.register::<components::Shape>();
 
         game_map.mark_tile(&target, TileProperties::BLOCKED);
 
@@ -152,4 +208,171 @@ mod tests {
             }
         };
     }
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::Moved>();
+        world.register::<components::material::Material>();
+        world.register::<components::Shape>();
+        world.insert(GameMap::new());
+
+        world
+    }
+
+    /// Adds a creature and puts it on the map, optionally trying to move
+    fn add_creature(
+        world: &mut World,
+        shape: components::Shape,
+        from: components::Position,
+        to: Option<components::Position>,
+    ) -> specs::Entity {
+        let mut builder = world
+            .create_entity()
+            .with(from.clone())
+            .with(components::material::flesh())
+            .with(shape.clone());
+
+        if let Some(to) = to {
+            builder = builder.with(components::Moved {
+                from: from.clone(),
+                to,
+            });
+        }
+
+        let entity = builder.build();
+        let properties = if shape.occupies_tile() {
+            TileProperties::OCCUPIED
+        } else {
+            TileProperties::empty()
+        };
+
+        world
+            .write_resource::<GameMap>()
+            .place(&from, entity, properties);
+
+        entity
+    }
+
+    fn collide(world: &mut World) {
+        CollisionsSolid.run_now(world);
+        world.maintain();
+    }
+
+    fn is_moving(world: &World, entity: specs::Entity) -> bool {
+        world.read_storage::<components::Moved>().contains(entity)
+    }
+
+    #[test]
+    fn creatures_cannot_share_a_tile() {
+        let mut world = build_world();
+        let pos = components::Position::new(0, 0);
+
+        add_creature(&mut world, components::Shape::Medium, pos.right(), None);
+        let mover = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            pos.clone(),
+            Some(pos.right()),
+        );
+
+        collide(&mut world);
+
+        assert!(!is_moving(&world, mover));
+    }
+
+    #[test]
+    fn small_things_can_share_a_tile() {
+        let mut world = build_world();
+        let pos = components::Position::new(0, 0);
+
+        add_creature(&mut world, components::Shape::Medium, pos.right(), None);
+        let mover = add_creature(
+            &mut world,
+            components::Shape::Small,
+            pos.clone(),
+            Some(pos.right()),
+        );
+
+        collide(&mut world);
+
+        assert!(is_moving(&world, mover));
+    }
+
+    #[test]
+    fn first_entity_wins_the_same_destination() {
+        let mut world = build_world();
+        let middle = components::Position::new(1, 0);
+
+        let first = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            middle.left(),
+            Some(middle.clone()),
+        );
+        let second = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            middle.right(),
+            Some(middle.clone()),
+        );
+
+        collide(&mut world);
+
+        assert!(is_moving(&world, first));
+        assert!(!is_moving(&world, second));
+    }
+
+    #[test]
+    fn can_follow_into_a_tile_being_left() {
+        let mut world = build_world();
+        let pos = components::Position::new(0, 0);
+
+        let follower = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            pos.clone(),
+            Some(pos.right()),
+        );
+        let leader = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            pos.right(),
+            Some(pos.right().right()),
+        );
+
+        collide(&mut world);
+
+        assert!(is_moving(&world, follower));
+        assert!(is_moving(&world, leader));
+    }
+
+    #[test]
+    fn blocked_leader_holds_up_the_follower() {
+        let mut world = build_world();
+        let pos = components::Position::new(0, 0);
+
+        world
+            .write_resource::<GameMap>()
+            .mark_tile(&pos.right().right(), TileProperties::BLOCKED);
+
+        let follower = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            pos.clone(),
+            Some(pos.right()),
+        );
+        let leader = add_creature(
+            &mut world,
+            components::Shape::Medium,
+            pos.right(),
+            Some(pos.right().right()),
+        );
+
+        collide(&mut world);
+
+        assert!(!is_moving(&world, follower));
+        assert!(!is_moving(&world, leader));
+    }
 }
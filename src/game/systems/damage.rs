@@ -0,0 +1,323 @@
+use super::super::{
+    components,
+    resources::{
+        self,
+        game_log::{self, GameLog},
+        DamageEvents,
+    },
+};
+use specs::{Read, ReadStorage, System, Write, WriteStorage};
+
+/// Applies all the damage done this dispatch.  Creatures that die leave a
+/// corpse behind if they're marked to and simply vanish otherwise.
+pub struct Damage;
+
+/// What's left of a creature, drawn in the same colors but faded
+fn corpse(sprite: &components::Sprite) -> components::Sprite {
+    components::Sprite {
+        fg_r: sprite.fg_r / 2,
+        fg_g: sprite.fg_g / 2,
+        fg_b: sprite.fg_b / 2,
+        layer: components::DL_CORPSES,
+        rune: '%',
+        ..sprite.clone()
+    }
+}
+
+/// How the log refers to something, the player being "you"
+fn describe(
+    entity: specs::Entity,
+    names: &ReadStorage<components::Name>,
+    player: Option<specs::Entity>,
+) -> String {
+    if player == Some(entity) {
+        return String::from("you");
+    }
+
+    match names.get(entity) {
+        Some(name) => format!("the {}", name.name),
+        None => String::from("something"),
+    }
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Picks the form of a verb that goes with the subject, "you" or otherwise
+fn verb<'v>(is_you: bool, you: &'v str, other: &'v str) -> &'v str {
+    if is_you {
+        you
+    } else {
+        other
+    }
+}
+
+impl<'a> System<'a> for Damage {
+    type SystemData = (
+        specs::Entities<'a>,
+        Write<'a, DamageEvents>,
+        WriteStorage<'a, components::Health>,
+        WriteStorage<'a, components::Sprite>,
+        WriteStorage<'a, components::Shape>,
+        WriteStorage<'a, components::Moved>,
+        ReadStorage<'a, components::LeavesCorpse>,
+        ReadStorage<'a, components::Name>,
+        Option<Read<'a, resources::Player>>,
+        Write<'a, GameLog>,
+        Read<'a, specs::LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut damage,
+            mut healths,
+            mut sprites,
+            mut shapes,
+            mut moved,
+            leaves_corpse,
+            names,
+            player,
+            mut log,
+            updater,
+        ): Self::SystemData,
+    ) {
+        let player = player.map(|player| player.ent);
+
+        for event in damage.pending.drain(..) {
+            let health = match healths.get_mut(event.target) {
+                Some(health) if !health.is_dead() => health,
+                _ => continue,
+            };
+
+            health.current -= event.amount;
+
+            let entity = event.target;
+            let is_player = player == Some(entity);
+            let target = describe(entity, &names, player);
+
+            let (text, color) = match event.source {
+                Some(source) => {
+                    let attacker = describe(source, &names, player);
+                    let hits = verb(player == Some(source), "hit", "hits");
+
+                    if event.amount > 0 {
+                        (
+                            format!("{} {} {} for {}.", attacker, hits, target, event.amount),
+                            if is_player {
+                                game_log::RED
+                            } else {
+                                game_log::WHITE
+                            },
+                        )
+                    } else {
+                        (
+                            format!("{} {} {} to no effect.", attacker, hits, target),
+                            game_log::WHITE,
+                        )
+                    }
+                }
+                None => (
+                    format!(
+                        "{} {} {} damage.",
+                        target,
+                        verb(is_player, "take", "takes"),
+                        event.amount
+                    ),
+                    game_log::ORANGE,
+                ),
+            };
+
+            log.push(capitalized(&text), color);
+
+            if !health.is_dead() {
+                continue;
+            }
+
+            if is_player {
+                log.push(String::from("You die..."), game_log::RED);
+            } else {
+                log.push(capitalized(&format!("{} dies.", target)), game_log::ORANGE);
+            }
+
+            if !leaves_corpse.contains(entity) {
+                entities.delete(entity).unwrap();
+                continue;
+            }
+
+            // Corpses keep their health so they're known to be dead, but
+            // can't act, fight or get in anyone's way any more
+            moved.remove(entity);
+            updater.remove::<components::Ai>(entity);
+            updater.remove::<components::Energy>(entity);
+            updater.remove::<components::Faction>(entity);
+            updater.remove::<components::Attack>(entity);
+            updater.remove::<components::Defense>(entity);
+
+            // The player's view is left alone so the map still gets drawn
+            if !is_player {
+                updater.remove::<components::Viewshed>(entity);
+            }
+
+            if let Some(sprite) = sprites.get_mut(entity) {
+                *sprite = corpse(sprite);
+            }
+
+            shapes.insert(entity, components::Shape::Small).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Health>();
+        world.register::<components::Sprite>();
+        world.register::<components::Shape>();
+        world.register::<components::Moved>();
+        world.register::<components::Energy>();
+        world.register::<components::Ai>();
+        world.register::<components::Faction>();
+        world.register::<components::Attack>();
+        world.register::<components::Defense>();
+        world.register::<components::Viewshed>();
+        world.register::<components::LeavesCorpse>();
+
+        world.register::<components::Name>();
+
+        world.insert(DamageEvents::default());
+        world.insert(GameLog::default());
+
+        world
+    }
+
+    fn add_creature(world: &mut World) -> specs::Entity {
+        world
+            .create_entity()
+            .with(components::Health::new(5))
+            .with(components::Energy::new(components::NORMAL_SPEED))
+            .with(components::Shape::Medium)
+            .with(components::LeavesCorpse)
+            .with(components::Sprite {
+                fg_r: 200,
+                fg_g: 100,
+                fg_b: 50,
+                bg_r: 0,
+                bg_g: 0,
+                bg_b: 0,
+                layer: components::DL_ENTITY,
+                rune: 'g',
+            })
+            .build()
+    }
+
+    fn hurt(world: &mut World, target: specs::Entity, amount: i32) {
+        world
+            .write_resource::<DamageEvents>()
+            .pending
+            .push(resources::DamageEvent {
+                target,
+                source: None,
+                amount,
+            });
+
+        Damage.run_now(world);
+        world.maintain();
+    }
+
+    #[test]
+    fn takes_away_health() {
+        let mut world = build_world();
+        let ent = add_creature(&mut world);
+
+        hurt(&mut world, ent, 3);
+
+        assert_eq!(
+            world.read_storage::<components::Health>().get(ent),
+            Some(&components::Health { current: 2, max: 5 })
+        );
+        assert!(world.read_resource::<DamageEvents>().pending.is_empty());
+    }
+
+    #[test]
+    fn dead_creatures_leave_a_corpse() {
+        let mut world = build_world();
+        let ent = add_creature(&mut world);
+
+        hurt(&mut world, ent, 5);
+
+        assert!(world.is_alive(ent));
+        assert!(!world.read_storage::<components::Energy>().contains(ent));
+        assert_eq!(
+            world.read_storage::<components::Shape>().get(ent),
+            Some(&components::Shape::Small)
+        );
+        assert_eq!(
+            world
+                .read_storage::<components::Sprite>()
+                .get(ent)
+                .unwrap()
+                .rune,
+            '%'
+        );
+    }
+
+    #[test]
+    fn tells_the_player_what_happened() {
+        let mut world = build_world();
+        let ent_player = world.create_entity().build();
+        let ent = add_creature(&mut world);
+
+        world.insert(resources::Player { ent: ent_player });
+        world
+            .write_storage::<components::Name>()
+            .insert(
+                ent,
+                components::Name {
+                    name: String::from("goblin"),
+                },
+            )
+            .unwrap();
+
+        world
+            .write_resource::<DamageEvents>()
+            .pending
+            .push(resources::DamageEvent {
+                target: ent,
+                source: Some(ent_player),
+                amount: 5,
+            });
+        Damage.run_now(&world);
+        world.maintain();
+
+        let log = world.read_resource::<GameLog>();
+        let texts: Vec<&str> = log.visible(2).iter().map(|m| m.text.as_str()).collect();
+
+        assert_eq!(texts, vec!["You hit the goblin for 5.", "The goblin dies."]);
+    }
+
+    #[test]
+    fn things_without_corpses_just_vanish() {
+        let mut world = build_world();
+        let ent = add_creature(&mut world);
+
+        world
+            .write_storage::<components::LeavesCorpse>()
+            .remove(ent);
+        hurt(&mut world, ent, 10);
+
+        assert!(!world.is_alive(ent));
+    }
+}
@@ -0,0 +1,317 @@
+use super::super::{
+    components::{
+        self,
+        fluid::{Fluid, FluidKind},
+        material::Material,
+        Burning,
+    },
+    level::Tile,
+    resources::{self, DamageEvent, DamageEvents, GameRng},
+};
+use rand::Rng;
+use specs::{Builder, Read, ReadStorage, System, Write, WriteStorage};
+use std::collections::HashMap;
+
+/// Heat a fire gives off on its own tile, with half as much reaching each
+/// tile next to it
+pub const FIRE_HEAT: f32 = 500.;
+
+/// Damage done each time unit to anything alive that's on fire
+pub const FIRE_DAMAGE: i32 = 2;
+
+/// Smoke a fire puts out each time unit
+const SMOKE_PER_TIME_UNIT: f32 = 0.3;
+
+/// Once per time unit, fires heat up whatever is around them, set alight
+/// anything flammable that gets hot enough and burn down.  Walls and floors
+/// that burn all the way leave ash behind.
+pub struct Fire;
+
+impl<'a> System<'a> for Fire {
+    type SystemData = (
+        specs::Entities<'a>,
+        ReadStorage<'a, components::Position>,
+        WriteStorage<'a, Burning>,
+        WriteStorage<'a, Material>,
+        WriteStorage<'a, components::Shape>,
+        WriteStorage<'a, components::Sprite>,
+        ReadStorage<'a, components::Health>,
+        Read<'a, resources::GameClock>,
+        Write<'a, GameRng>,
+        Write<'a, DamageEvents>,
+        Read<'a, specs::LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            positions,
+            mut burning,
+            mut materials,
+            mut shapes,
+            mut sprites,
+            healths,
+            clock,
+            mut rng,
+            mut damage,
+            updater,
+        ): Self::SystemData,
+    ) {
+        use specs::Join;
+
+        if !clock.ticked {
+            return;
+        }
+
+        let mut heat: HashMap<components::Position, f32> = HashMap::new();
+        let mut burnt_out = Vec::new();
+
+        for (entity, pos, fire) in (&entities, &positions, &mut burning).join() {
+            *heat.entry(pos.clone()).or_insert(0.) += FIRE_HEAT;
+
+            for neighbor in &[pos.up(), pos.down(), pos.left(), pos.right()] {
+                *heat.entry(neighbor.clone()).or_insert(0.) += FIRE_HEAT / 2.;
+            }
+
+            if healths.contains(entity) {
+                damage.pending.push(DamageEvent {
+                    target: entity,
+                    source: None,
+                    amount: FIRE_DAMAGE,
+                });
+            }
+
+            let smoke = FluidKind::Smoke;
+
+            updater
+                .create_entity(&entities)
+                .with(pos.clone())
+                .with(smoke.sprite())
+                .with(smoke.material(SMOKE_PER_TIME_UNIT))
+                .with(components::Shape::FullBlock)
+                .with(Fluid {
+                    kind: smoke,
+                    volume: SMOKE_PER_TIME_UNIT,
+                })
+                .build();
+
+            fire.remaining = fire.remaining.saturating_sub(1);
+
+            if fire.remaining == 0 {
+                burnt_out.push(entity);
+            }
+        }
+
+        let mut ignited = Vec::new();
+
+        for (entity, pos, material, _) in (&entities, &positions, &materials, !&burning).join() {
+            if material.flammability <= 0. {
+                continue;
+            }
+
+            let temperature = heat.get(pos).cloned().unwrap_or(0.);
+
+            if temperature >= material.ignition_temperature
+                && rng.0.gen::<f32>() < material.flammability
+            {
+                ignited.push((entity, material.burn_duration.max(1)));
+            }
+        }
+
+        for entity in burnt_out {
+            burning.remove(entity);
+
+            // Creatures just stop burning, but there's nothing left of terrain
+            match shapes.get(entity) {
+                Some(components::Shape::Floor) | Some(components::Shape::FullBlock) => {
+                    let ash = Tile::ash_floor();
+
+                    materials.insert(entity, ash.material).unwrap();
+                    shapes.insert(entity, ash.shape).unwrap();
+                    sprites.insert(entity, ash.sprite).unwrap();
+                }
+                _ => (),
+            }
+        }
+
+        for (entity, remaining) in ignited {
+            burning.insert(entity, Burning { remaining }).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{RunNow, World, WorldExt};
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::Sprite>();
+        world.register::<components::Shape>();
+        world.register::<Material>();
+        world.register::<Burning>();
+        world.register::<Fluid>();
+        world.register::<components::Health>();
+
+        world.insert(GameRng::default());
+        world.insert(DamageEvents::default());
+        world.insert(resources::GameClock {
+            time: 0,
+            ticked: true,
+        });
+
+        world
+    }
+
+    fn add_wall(world: &mut World, x: i32, material: Material) -> specs::Entity {
+        let wall = Tile::stone_wall();
+
+        world
+            .create_entity()
+            .with(components::Position::new(x, 0))
+            .with(wall.sprite)
+            .with(material)
+            .with(wall.shape)
+            .build()
+    }
+
+    fn set_alight(world: &mut World, entity: specs::Entity) {
+        world
+            .write_storage::<Burning>()
+            .insert(entity, Burning { remaining: 10 })
+            .unwrap();
+    }
+
+    fn step(world: &mut World, times: usize) {
+        for _ in 0..times {
+            Fire.run_now(world);
+            world.maintain();
+        }
+    }
+
+    fn is_burning(world: &World, entity: specs::Entity) -> bool {
+        world.read_storage::<Burning>().contains(entity)
+    }
+
+    #[test]
+    fn spreads_to_wood_next_to_it() {
+        let mut world = build_world();
+        let first = add_wall(&mut world, 0, components::material::wood());
+        let second = add_wall(&mut world, 1, components::material::wood());
+        let third = add_wall(&mut world, 3, components::material::wood());
+
+        set_alight(&mut world, first);
+        step(&mut world, 8);
+
+        assert!(is_burning(&world, second));
+        assert!(!is_burning(&world, third));
+    }
+
+    #[test]
+    fn stone_never_burns() {
+        let mut world = build_world();
+        let wood = add_wall(&mut world, 0, components::material::wood());
+        let stone = add_wall(&mut world, 1, components::material::stone());
+
+        set_alight(&mut world, wood);
+        step(&mut world, 8);
+
+        assert!(!is_burning(&world, stone));
+    }
+
+    #[test]
+    fn flesh_burns_when_caught_in_it() {
+        let mut world = build_world();
+        let fire = add_wall(&mut world, 0, components::material::wood());
+        let creature = world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::material::flesh())
+            .with(components::Shape::Medium)
+            .build();
+
+        set_alight(&mut world, fire);
+        step(&mut world, 8);
+
+        assert!(is_burning(&world, creature));
+    }
+
+    #[test]
+    fn burnt_walls_turn_to_floor() {
+        let mut world = build_world();
+        let wall = add_wall(&mut world, 0, components::material::wood());
+
+        world
+            .write_storage::<Burning>()
+            .insert(wall, Burning { remaining: 2 })
+            .unwrap();
+        step(&mut world, 2);
+
+        assert!(!is_burning(&world, wall));
+        assert_eq!(
+            world.read_storage::<components::Shape>().get(wall),
+            Some(&components::Shape::Floor)
+        );
+        assert_eq!(
+            world.read_storage::<Material>().get(wall),
+            Some(&components::material::ash())
+        );
+
+        // Nothing is left standing in the way
+        let ash = components::material::ash();
+
+        assert!(!ash.solid);
+        assert_eq!(ash.opacity, 0.);
+    }
+
+    #[test]
+    fn gives_off_smoke() {
+        let mut world = build_world();
+        let wall = add_wall(&mut world, 0, components::material::wood());
+
+        set_alight(&mut world, wall);
+        step(&mut world, 1);
+
+        let fluids = world.read_storage::<Fluid>();
+
+        assert_eq!(fluids.count(), 1);
+    }
+
+    #[test]
+    fn hurts_whatever_is_burning() {
+        let mut world = build_world();
+        let creature = world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::Health::new(10))
+            .build();
+
+        set_alight(&mut world, creature);
+        step(&mut world, 1);
+
+        let damage = world.read_resource::<DamageEvents>();
+
+        assert_eq!(damage.pending.len(), 1);
+        assert_eq!(damage.pending[0].target, creature);
+        assert_eq!(damage.pending[0].amount, FIRE_DAMAGE);
+    }
+
+    #[test]
+    fn waits_for_time_to_pass() {
+        let mut world = build_world();
+        let wall = add_wall(&mut world, 0, components::material::wood());
+
+        world.insert(resources::GameClock::default());
+        set_alight(&mut world, wall);
+        step(&mut world, 3);
+
+        assert_eq!(
+            world.read_storage::<Burning>().get(wall),
+            Some(&Burning { remaining: 10 })
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use super::super::{
+    components::{self, fluid::Fluid},
+    resources::game_map::GameMap,
+};
+use specs::{Read, ReadStorage, System, WriteStorage};
+
+/// Wading into deep fluids takes extra energy on top of the move itself
+pub struct FluidDrag;
+
+impl<'a> System<'a> for FluidDrag {
+    type SystemData = (
+        ReadStorage<'a, components::Moved>,
+        ReadStorage<'a, Fluid>,
+        WriteStorage<'a, components::Energy>,
+        Read<'a, GameMap>,
+    );
+
+    fn run(&mut self, (moved, fluids, mut energy, game_map): Self::SystemData) {
+        use specs::Join;
+
+        for (mv, energy) in (&moved, &mut energy).join() {
+            let cost = game_map
+                .get_entities(&mv.to)
+                .into_iter()
+                .flatten()
+                .filter_map(|entity| fluids.get(*entity))
+                .map(|fluid| fluid.kind.wading_cost(fluid.volume))
+                .max()
+                .unwrap_or(0);
+
+            energy.value -= cost;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::components::fluid::FluidKind;
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn wade(kind: FluidKind, volume: f32) -> i32 {
+        let mut world = World::new();
+        let start = components::Position::new(0, 0);
+        let target = start.right();
+        let mut game_map = GameMap::new();
+
+        world.register::<components::Moved>();
+        world.register::<components::Energy>();
+        world.register::<Fluid>();
+
+        let ent_fluid = world.create_entity().with(Fluid { kind, volume }).build();
+
+        game_map.add(&target, ent_fluid);
+        world.insert(game_map);
+
+        let ent_mover = world
+            .create_entity()
+            .with(components::Moved {
+                from: start,
+                to: target,
+            })
+            .with(components::Energy::new(components::NORMAL_SPEED))
+            .build();
+
+        FluidDrag.run_now(&world);
+        world.maintain();
+
+        let energy = world.read_storage::<components::Energy>();
+
+        -energy.get(ent_mover).unwrap().value
+    }
+
+    #[test]
+    fn deep_water_slows_movement() {
+        assert_eq!(wade(FluidKind::Water, 1.), components::ACTION_COST / 2);
+    }
+
+    #[test]
+    fn shallow_water_and_gas_do_not() {
+        assert_eq!(wade(FluidKind::Water, 0.2), 0);
+        assert_eq!(wade(FluidKind::Smoke, 1.), 0);
+    }
+}
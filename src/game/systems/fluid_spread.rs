@@ -0,0 +1,295 @@
+use super::super::{
+    components::{
+        self,
+        fluid::{Fluid, FluidKind, MIN_VOLUME},
+    },
+    pathfinding,
+    resources::{self, game_map::GameMap},
+};
+use specs::{Builder, Read, ReadStorage, System, WriteStorage};
+use std::collections::BTreeMap;
+
+/// Lets gases and liquids flow into neighboring open tiles and thin out, once
+/// per time unit.  Each tile holds at most one entity per kind of fluid.
+pub struct FluidSpread;
+
+// Ordered by kind then row then column, so the simulation always runs the same way
+type Cell = (FluidKind, i32, i32);
+
+impl<'a> System<'a> for FluidSpread {
+    type SystemData = (
+        specs::Entities<'a>,
+        ReadStorage<'a, components::Position>,
+        WriteStorage<'a, Fluid>,
+        WriteStorage<'a, components::material::Material>,
+        Read<'a, resources::GameClock>,
+        Read<'a, GameMap>,
+        Read<'a, specs::LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, positions, mut fluids, mut materials, clock, game_map, updater): Self::SystemData,
+    ) {
+        use specs::Join;
+
+        if !clock.ticked {
+            return;
+        }
+
+        let mut current: BTreeMap<Cell, (specs::Entity, f32)> = BTreeMap::new();
+
+        for (entity, pos, fluid) in (&entities, &positions, &fluids).join() {
+            let cell = (fluid.kind, pos.y, pos.x);
+
+            match current.get_mut(&cell) {
+                // Two of the same fluid on a tile just pool together
+                Some((_, volume)) => {
+                    *volume += fluid.volume;
+                    entities.delete(entity).unwrap();
+                }
+                None => {
+                    current.insert(cell, (entity, fluid.volume));
+                }
+            }
+        }
+
+        let mut next: BTreeMap<Cell, f32> = BTreeMap::new();
+
+        for ((kind, y, x), (_, volume)) in &current {
+            let pos = components::Position::new(*x, *y);
+            let open: Vec<components::Position> =
+                vec![pos.up(), pos.down(), pos.left(), pos.right()]
+                    .into_iter()
+                    .filter(|neighbor| pathfinding::is_walkable(&game_map, neighbor))
+                    .collect();
+
+            let share = volume * kind.spread() / 4.;
+
+            // Too little to split up any further, so it stays where it is
+            if share < MIN_VOLUME {
+                *next.entry((*kind, *y, *x)).or_insert(0.) += volume;
+                continue;
+            }
+
+            *next.entry((*kind, *y, *x)).or_insert(0.) += volume - share * open.len() as f32;
+
+            for neighbor in open {
+                *next.entry((*kind, neighbor.y, neighbor.x)).or_insert(0.) += share;
+            }
+        }
+
+        for ((kind, y, x), volume) in next {
+            let volume = volume * (1. - kind.dissipation());
+
+            match current.get(&(kind, y, x)) {
+                Some((entity, _)) if volume < MIN_VOLUME && kind.evaporates() => {
+                    entities.delete(*entity).unwrap();
+                }
+                Some((entity, _)) => {
+                    if let Some(fluid) = fluids.get_mut(*entity) {
+                        fluid.volume = volume;
+                    }
+
+                    // Only touch the material when it actually changes, since
+                    // that makes everything that can see it look again
+                    let material = kind.material(volume);
+
+                    if materials.get(*entity) != Some(&material) {
+                        materials.insert(*entity, material).unwrap();
+                    }
+                }
+                None if volume >= MIN_VOLUME || !kind.evaporates() => {
+                    updater
+                        .create_entity(&entities)
+                        .with(components::Position::new(x, y))
+                        .with(kind.sprite())
+                        .with(kind.material(volume))
+                        .with(components::Shape::FullBlock)
+                        .with(Fluid { kind, volume })
+                        .build();
+                }
+                None => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::{entities, pathfinding::test_map};
+    use super::*;
+    use specs::{RunNow, World, WorldExt};
+
+    fn build_world(rows: &[&str]) -> World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::Sprite>();
+        world.register::<components::Shape>();
+        world.register::<components::material::Material>();
+        world.register::<Fluid>();
+
+        world.insert(test_map::build(rows));
+        world.insert(resources::GameClock {
+            time: 0,
+            ticked: true,
+        });
+
+        world
+    }
+
+    fn step(world: &mut World) {
+        FluidSpread.run_now(world);
+        world.maintain();
+    }
+
+    fn volumes(world: &World) -> BTreeMap<(i32, i32), f32> {
+        use specs::Join;
+
+        let positions = world.read_storage::<components::Position>();
+        let fluids = world.read_storage::<Fluid>();
+
+        (&positions, &fluids)
+            .join()
+            .map(|(pos, fluid)| ((pos.x, pos.y), fluid.volume))
+            .collect()
+    }
+
+    #[test]
+    fn spreads_into_open_tiles_but_not_walls() {
+        let mut world = build_world(&[
+            "#.#", //
+            "...", //
+            "###", //
+        ]);
+
+        entities::fluid::create_in(
+            &mut world,
+            components::Position::new(1, 1),
+            FluidKind::Smoke,
+            1.,
+        );
+
+        step(&mut world);
+
+        let volumes = volumes(&world);
+
+        assert!(volumes.contains_key(&(1, 0)));
+        assert!(volumes.contains_key(&(0, 1)));
+        assert!(volumes.contains_key(&(2, 1)));
+        assert!(!volumes.contains_key(&(1, 2)));
+        assert!(volumes[&(1, 1)] > volumes[&(1, 0)]);
+    }
+
+    #[test]
+    fn water_keeps_its_volume() {
+        let mut world = build_world(&[
+            ".....", //
+            ".....", //
+            ".....", //
+        ]);
+
+        entities::fluid::create_in(
+            &mut world,
+            components::Position::new(2, 1),
+            FluidKind::Water,
+            2.,
+        );
+
+        // A puddle too thin to ever spread stays around too
+        entities::fluid::create_in(
+            &mut world,
+            components::Position::new(0, 0),
+            FluidKind::Water,
+            MIN_VOLUME / 2.,
+        );
+
+        for _ in 0..200 {
+            step(&mut world);
+        }
+
+        let volumes = volumes(&world);
+        let total: f32 = volumes.values().sum();
+
+        // Long enough that it's spread as thin as it ever gets
+        assert!(volumes.len() > 2);
+        assert!(volumes.values().all(|volume| *volume < 1.));
+        assert!((total - 2. - MIN_VOLUME / 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn smoke_thins_out_and_disappears() {
+        let mut world = build_world(&["..."]);
+
+        entities::fluid::create_in(
+            &mut world,
+            components::Position::new(1, 0),
+            FluidKind::Smoke,
+            1.,
+        );
+
+        step(&mut world);
+
+        let thinner: f32 = volumes(&world).values().sum();
+
+        assert!(thinner < 1.);
+
+        for _ in 0..100 {
+            step(&mut world);
+        }
+
+        assert!(volumes(&world).is_empty());
+    }
+
+    #[test]
+    fn gets_easier_to_see_through_as_it_spreads() {
+        let mut world = build_world(&["....."]);
+
+        let ent = entities::fluid::create_in(
+            &mut world,
+            components::Position::new(2, 0),
+            FluidKind::Smoke,
+            1.,
+        );
+
+        step(&mut world);
+
+        let materials = world.read_storage::<components::material::Material>();
+
+        assert!(materials.get(ent).unwrap().opacity < FluidKind::Smoke.material(1.).opacity);
+    }
+
+    #[test]
+    fn pools_together_on_the_same_tile() {
+        let mut world = build_world(&["#.#"]);
+        let pos = components::Position::new(1, 0);
+
+        entities::fluid::create_in(&mut world, pos.clone(), FluidKind::Water, 0.5);
+        entities::fluid::create_in(&mut world, pos, FluidKind::Water, 0.25);
+
+        step(&mut world);
+
+        let volumes = volumes(&world);
+
+        assert_eq!(volumes.len(), 1);
+        assert!((volumes[&(1, 0)] - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn waits_for_time_to_pass() {
+        let mut world = build_world(&["..."]);
+
+        world.insert(resources::GameClock::default());
+        entities::fluid::create_in(
+            &mut world,
+            components::Position::new(1, 0),
+            FluidKind::Smoke,
+            1.,
+        );
+
+        step(&mut world);
+
+        assert_eq!(volumes(&world).len(), 1);
+    }
+}
@@ -0,0 +1,694 @@
+use super::super::{
+    components::{self, equipment},
+    input,
+    resources::{
+        self,
+        game_log::{self, GameLog},
+        game_map::{GameMap, TileProperties},
+    },
+};
+use specs::{Read, ReadStorage, System, Write, WriteStorage};
+
+/// Picks up, drops, uses and equips items for the player.  Each of these takes
+/// a turn, except when there turns out to be nothing to do it with.
+pub struct ItemActions;
+
+fn item_name(item: specs::Entity, names: &ReadStorage<components::Name>) -> String {
+    names
+        .get(item)
+        .map_or_else(|| String::from("item"), |name| name.name.clone())
+}
+
+impl<'a> System<'a> for ItemActions {
+    type SystemData = (
+        specs::Entities<'a>,
+        Option<Read<'a, resources::Player>>,
+        Read<'a, resources::PendingAction>,
+        Write<'a, resources::InventoryScreen>,
+        Read<'a, GameMap>,
+        WriteStorage<'a, components::Position>,
+        WriteStorage<'a, components::Energy>,
+        WriteStorage<'a, components::Inventory>,
+        WriteStorage<'a, components::Carried>,
+        WriteStorage<'a, components::Health>,
+        WriteStorage<'a, equipment::Equipment>,
+        WriteStorage<'a, components::Viewshed>,
+        ReadStorage<'a, components::Item>,
+        ReadStorage<'a, equipment::Equippable>,
+        ReadStorage<'a, components::Name>,
+        Write<'a, GameLog>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            player,
+            pending_action,
+            mut screen,
+            game_map,
+            mut positions,
+            mut energy,
+            mut inventories,
+            mut carried,
+            mut healths,
+            mut equipment,
+            mut viewsheds,
+            items,
+            equippables,
+            names,
+            mut log,
+        ): Self::SystemData,
+    ) {
+        let player = match player {
+            Some(player) => player.ent,
+            None => return,
+        };
+
+        // Same as moving, this has to wait for the player's turn
+        if energy.get(player).is_some_and(|energy| !energy.is_ready()) {
+            return;
+        }
+
+        let pos = match positions.get(player) {
+            Some(pos) => pos.clone(),
+            None => return,
+        };
+
+        let inventory = match inventories.get_mut(player) {
+            Some(inventory) => inventory,
+            None => return,
+        };
+
+        let mut equipment = equipment.get_mut(player);
+        let selected = screen.selected.min(inventory.items.len().saturating_sub(1));
+
+        // Anything equipped could change how far the player sees
+        let mut equipment_changed = false;
+
+        let takes_turn = match pending_action.0 {
+            Some(input::Action::PickUp) => {
+                // Oldest first, so the same pile always gives up the same item
+                let item = game_map
+                    .get_entities(&pos)
+                    .filter(|_| game_map.tile_is(&pos, TileProperties::ITEMS))
+                    .into_iter()
+                    .flatten()
+                    .filter(|entity| items.contains(**entity) && !carried.contains(**entity))
+                    .min_by_key(|entity| entity.id())
+                    .copied();
+
+                match item {
+                    None => {
+                        log.push(
+                            String::from("There is nothing here to pick up."),
+                            game_log::WHITE,
+                        );
+                        false
+                    }
+                    Some(_) if inventory.is_full() => {
+                        log.push(String::from("You can't carry any more."), game_log::WHITE);
+                        false
+                    }
+                    Some(item) => {
+                        positions.remove(item);
+                        carried
+                            .insert(item, components::Carried { by: player })
+                            .unwrap();
+                        inventory.items.push(item);
+
+                        log.push(
+                            format!("You pick up the {}.", item_name(item, &names)),
+                            game_log::WHITE,
+                        );
+                        true
+                    }
+                }
+            }
+
+            Some(input::Action::Drop) => {
+                if inventory.items.is_empty() {
+                    log.push(String::from("You have nothing to drop."), game_log::WHITE);
+                    false
+                } else {
+                    let item = inventory.items.remove(selected);
+
+                    // Whatever's dropped has to come off first
+                    if let Some(equipment) = equipment.as_mut() {
+                        if let Some(slot) = equipment.slot_of(item) {
+                            *equipment.get_mut(slot) = None;
+                            equipment_changed = true;
+                        }
+                    }
+
+                    carried.remove(item);
+                    positions.insert(item, pos).unwrap();
+
+                    log.push(
+                        format!("You drop the {}.", item_name(item, &names)),
+                        game_log::WHITE,
+                    );
+                    true
+                }
+            }
+
+            Some(input::Action::Use) => {
+                let item = inventory.items.get(selected).copied();
+                let effect = item.and_then(|item| items.get(item)).and_then(|i| i.effect);
+
+                match (item, effect) {
+                    (None, _) => {
+                        log.push(String::from("You have nothing to use."), game_log::WHITE);
+                        false
+                    }
+                    (Some(item), None) => {
+                        log.push(
+                            format!("You can't use the {}.", item_name(item, &names)),
+                            game_log::WHITE,
+                        );
+                        false
+                    }
+                    (Some(item), Some(components::ItemEffect::Heal(amount))) => {
+                        let healed = healths.get_mut(player).map_or(0, |health| {
+                            let healed = amount.min(health.max - health.current).max(0);
+
+                            health.current += healed;
+                            healed
+                        });
+
+                        log.push(
+                            format!(
+                                "You drink the {} and recover {} health.",
+                                item_name(item, &names),
+                                healed
+                            ),
+                            game_log::GREEN,
+                        );
+
+                        inventory.items.remove(selected);
+                        entities.delete(item).unwrap();
+                        true
+                    }
+                }
+            }
+
+            Some(input::Action::Equip) => {
+                let item = inventory.items.get(selected).copied();
+                let slot = item
+                    .and_then(|item| equippables.get(item))
+                    .map(|equippable| equippable.slot);
+
+                match (item, slot, equipment.as_mut()) {
+                    (None, _, _) => {
+                        log.push(String::from("You have nothing to equip."), game_log::WHITE);
+                        false
+                    }
+                    (Some(item), None, _) | (Some(item), _, None) => {
+                        log.push(
+                            format!("You can't equip the {}.", item_name(item, &names)),
+                            game_log::WHITE,
+                        );
+                        false
+                    }
+                    (Some(item), Some(slot), Some(equipment)) => {
+                        let slot_item = equipment.get_mut(slot);
+
+                        match slot_item.replace(item) {
+                            Some(old) if old == item => {
+                                log.push(
+                                    format!(
+                                        "You already have the {} equipped.",
+                                        item_name(item, &names)
+                                    ),
+                                    game_log::WHITE,
+                                );
+                                false
+                            }
+                            Some(old) => {
+                                log.push(
+                                    format!(
+                                        "You swap the {} for the {}.",
+                                        item_name(old, &names),
+                                        item_name(item, &names)
+                                    ),
+                                    game_log::WHITE,
+                                );
+                                equipment_changed = true;
+                                true
+                            }
+                            None => {
+                                log.push(
+                                    format!("You equip the {}.", item_name(item, &names)),
+                                    game_log::WHITE,
+                                );
+                                equipment_changed = true;
+                                true
+                            }
+                        }
+                    }
+                }
+            }
+
+            Some(input::Action::Unequip) => {
+                let item = inventory.items.get(selected).copied();
+                let slot = item.and_then(|item| {
+                    equipment
+                        .as_ref()
+                        .and_then(|equipment| equipment.slot_of(item))
+                });
+
+                match (item, slot, equipment.as_mut()) {
+                    (Some(item), Some(slot), Some(equipment)) => {
+                        *equipment.get_mut(slot) = None;
+                        equipment_changed = true;
+
+                        log.push(
+                            format!("You take off the {}.", item_name(item, &names)),
+                            game_log::WHITE,
+                        );
+                        true
+                    }
+                    (Some(item), _, _) => {
+                        log.push(
+                            format!("The {} isn't equipped.", item_name(item, &names)),
+                            game_log::WHITE,
+                        );
+                        false
+                    }
+                    (None, _, _) => {
+                        log.push(
+                            String::from("You have nothing to take off."),
+                            game_log::WHITE,
+                        );
+                        false
+                    }
+                }
+            }
+
+            _ => false,
+        };
+
+        screen.clamp(inventory.items.len());
+
+        if equipment_changed {
+            if let Some(viewshed) = viewsheds.get_mut(player) {
+                viewshed.dirty = true;
+            }
+        }
+
+        if takes_turn {
+            if let Some(energy) = energy.get_mut(player) {
+                energy.spend();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world(action: input::Action) -> (World, specs::Entity) {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::Energy>();
+        world.register::<components::Inventory>();
+        world.register::<components::Carried>();
+        world.register::<components::Health>();
+        world.register::<components::Item>();
+        world.register::<components::Name>();
+        world.register::<components::Viewshed>();
+        world.register::<equipment::Equipment>();
+        world.register::<equipment::Equippable>();
+
+        world.insert(resources::PendingAction(Some(action)));
+        world.insert(resources::InventoryScreen::default());
+        world.insert(GameMap::new());
+        world.insert(GameLog::default());
+
+        let player = world
+            .create_entity()
+            .with(components::Position::new(2, 2))
+            .with(components::Energy {
+                value: components::ACTION_COST,
+                speed: components::NORMAL_SPEED,
+            })
+            .with(components::Inventory::new(2))
+            .with(equipment::Equipment::default())
+            .with(components::Health {
+                current: 5,
+                max: 30,
+            })
+            .build();
+
+        world.insert(resources::Player { ent: player });
+
+        (world, player)
+    }
+
+    fn potion(world: &mut World) -> specs::Entity {
+        world
+            .create_entity()
+            .with(components::Name {
+                name: String::from("potion"),
+            })
+            .with(components::Item {
+                effect: Some(components::ItemEffect::Heal(10)),
+            })
+            .build()
+    }
+
+    fn sword(world: &mut World) -> specs::Entity {
+        world
+            .create_entity()
+            .with(components::Name {
+                name: String::from("sword"),
+            })
+            .with(components::Item { effect: None })
+            .with(equipment::Equippable {
+                slot: equipment::Slot::Weapon,
+                attack: 3,
+                vision: 0,
+            })
+            .build()
+    }
+
+    fn equipped(world: &World, player: specs::Entity) -> equipment::Equipment {
+        world
+            .read_storage::<equipment::Equipment>()
+            .get(player)
+            .unwrap()
+            .clone()
+    }
+
+    /// Puts an item on the floor, as the game map would see it
+    fn drop_at(world: &mut World, item: specs::Entity, pos: components::Position) {
+        let mut game_map = world.write_resource::<GameMap>();
+
+        game_map.place(&pos, item, TileProperties::ITEMS);
+        world
+            .write_storage::<components::Position>()
+            .insert(item, pos)
+            .unwrap();
+    }
+
+    fn carry(world: &mut World, player: specs::Entity, item: specs::Entity) {
+        world
+            .write_storage::<components::Inventory>()
+            .get_mut(player)
+            .unwrap()
+            .items
+            .push(item);
+        world
+            .write_storage::<components::Carried>()
+            .insert(item, components::Carried { by: player })
+            .unwrap();
+    }
+
+    fn run(world: &mut World) {
+        ItemActions.run_now(world);
+        world.maintain();
+    }
+
+    fn energy(world: &World, player: specs::Entity) -> i32 {
+        world
+            .read_storage::<components::Energy>()
+            .get(player)
+            .unwrap()
+            .value
+    }
+
+    fn last_message(world: &World) -> String {
+        world.read_resource::<GameLog>().visible(1)[0].text.clone()
+    }
+
+    #[test]
+    fn picks_up_item_underfoot() {
+        let (mut world, player) = build_world(input::Action::PickUp);
+        let item = potion(&mut world);
+
+        drop_at(&mut world, item, components::Position::new(2, 2));
+        run(&mut world);
+
+        assert_eq!(
+            world
+                .read_storage::<components::Inventory>()
+                .get(player)
+                .unwrap()
+                .items,
+            vec![item]
+        );
+        assert!(world
+            .read_storage::<components::Position>()
+            .get(item)
+            .is_none());
+        assert_eq!(
+            world.read_storage::<components::Carried>().get(item),
+            Some(&components::Carried { by: player })
+        );
+        assert_eq!(energy(&world, player), 0);
+        assert_eq!(last_message(&world), "You pick up the potion.");
+    }
+
+    #[test]
+    fn nothing_to_pick_up_takes_no_turn() {
+        let (mut world, player) = build_world(input::Action::PickUp);
+        let item = potion(&mut world);
+
+        drop_at(&mut world, item, components::Position::new(3, 2));
+        run(&mut world);
+
+        assert!(world
+            .read_storage::<components::Inventory>()
+            .get(player)
+            .unwrap()
+            .items
+            .is_empty());
+        assert_eq!(energy(&world, player), components::ACTION_COST);
+    }
+
+    #[test]
+    fn cant_pick_up_more_than_capacity() {
+        let (mut world, player) = build_world(input::Action::PickUp);
+
+        for _ in 0..2 {
+            let item = potion(&mut world);
+
+            carry(&mut world, player, item);
+        }
+
+        let item = potion(&mut world);
+
+        drop_at(&mut world, item, components::Position::new(2, 2));
+        run(&mut world);
+
+        assert!(world
+            .read_storage::<components::Position>()
+            .get(item)
+            .is_some());
+        assert_eq!(last_message(&world), "You can't carry any more.");
+        assert_eq!(energy(&world, player), components::ACTION_COST);
+    }
+
+    #[test]
+    fn drops_selected_item_where_player_stands() {
+        let (mut world, player) = build_world(input::Action::Drop);
+        let first = potion(&mut world);
+        let second = potion(&mut world);
+
+        carry(&mut world, player, first);
+        carry(&mut world, player, second);
+        world
+            .write_resource::<resources::InventoryScreen>()
+            .selected = 1;
+
+        run(&mut world);
+
+        assert_eq!(
+            world
+                .read_storage::<components::Inventory>()
+                .get(player)
+                .unwrap()
+                .items,
+            vec![first]
+        );
+        assert_eq!(
+            world.read_storage::<components::Position>().get(second),
+            Some(&components::Position::new(2, 2))
+        );
+        assert!(world
+            .read_storage::<components::Carried>()
+            .get(second)
+            .is_none());
+
+        // The selection stays on an item that's still there
+        assert_eq!(
+            world.read_resource::<resources::InventoryScreen>().selected,
+            0
+        );
+        assert_eq!(energy(&world, player), 0);
+    }
+
+    #[test]
+    fn healing_potions_heal_and_get_used_up() {
+        let (mut world, player) = build_world(input::Action::Use);
+        let item = potion(&mut world);
+
+        carry(&mut world, player, item);
+        run(&mut world);
+
+        assert_eq!(
+            world
+                .read_storage::<components::Health>()
+                .get(player)
+                .unwrap()
+                .current,
+            15
+        );
+        assert!(!world.entities().is_alive(item));
+        assert!(world
+            .read_storage::<components::Inventory>()
+            .get(player)
+            .unwrap()
+            .items
+            .is_empty());
+        assert_eq!(
+            last_message(&world),
+            "You drink the potion and recover 10 health."
+        );
+    }
+
+    #[test]
+    fn waits_for_turn_without_energy() {
+        let (mut world, player) = build_world(input::Action::PickUp);
+        let item = potion(&mut world);
+
+        drop_at(&mut world, item, components::Position::new(2, 2));
+        world
+            .write_storage::<components::Energy>()
+            .insert(player, components::Energy::new(components::NORMAL_SPEED))
+            .unwrap();
+
+        run(&mut world);
+
+        assert!(world
+            .read_storage::<components::Carried>()
+            .get(item)
+            .is_none());
+    }
+
+    #[test]
+    fn equipping_fills_the_slot_and_swaps_out_the_old_item() {
+        let (mut world, player) = build_world(input::Action::Equip);
+        let first = sword(&mut world);
+        let second = sword(&mut world);
+
+        world
+            .write_storage::<components::Name>()
+            .insert(
+                second,
+                components::Name {
+                    name: String::from("axe"),
+                },
+            )
+            .unwrap();
+
+        carry(&mut world, player, first);
+        carry(&mut world, player, second);
+        run(&mut world);
+
+        assert_eq!(equipped(&world, player).weapon, Some(first));
+        assert_eq!(energy(&world, player), 0);
+
+        world
+            .write_storage::<components::Energy>()
+            .get_mut(player)
+            .unwrap()
+            .value = components::ACTION_COST;
+        world
+            .write_resource::<resources::InventoryScreen>()
+            .selected = 1;
+        run(&mut world);
+
+        assert_eq!(equipped(&world, player).weapon, Some(second));
+        assert_eq!(last_message(&world), "You swap the sword for the axe.");
+
+        // Both stay in the inventory either way
+        assert_eq!(
+            world
+                .read_storage::<components::Inventory>()
+                .get(player)
+                .unwrap()
+                .items
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn cant_equip_potions() {
+        let (mut world, player) = build_world(input::Action::Equip);
+        let item = potion(&mut world);
+
+        carry(&mut world, player, item);
+        run(&mut world);
+
+        assert_eq!(equipped(&world, player), equipment::Equipment::default());
+        assert_eq!(last_message(&world), "You can't equip the potion.");
+        assert_eq!(energy(&world, player), components::ACTION_COST);
+    }
+
+    #[test]
+    fn unequipping_empties_the_slot() {
+        let (mut world, player) = build_world(input::Action::Unequip);
+        let item = sword(&mut world);
+
+        carry(&mut world, player, item);
+        world
+            .write_storage::<equipment::Equipment>()
+            .get_mut(player)
+            .unwrap()
+            .weapon = Some(item);
+
+        run(&mut world);
+
+        assert_eq!(equipped(&world, player).weapon, None);
+        assert_eq!(last_message(&world), "You take off the sword.");
+    }
+
+    #[test]
+    fn dropping_equipped_items_takes_them_off() {
+        let (mut world, player) = build_world(input::Action::Drop);
+        let item = sword(&mut world);
+
+        world
+            .write_storage::<components::Viewshed>()
+            .insert(player, components::Viewshed::new(5))
+            .unwrap();
+        world
+            .write_storage::<components::Viewshed>()
+            .get_mut(player)
+            .unwrap()
+            .dirty = false;
+
+        carry(&mut world, player, item);
+        world
+            .write_storage::<equipment::Equipment>()
+            .get_mut(player)
+            .unwrap()
+            .weapon = Some(item);
+
+        run(&mut world);
+
+        assert_eq!(equipped(&world, player).weapon, None);
+        assert!(
+            world
+                .read_storage::<components::Viewshed>()
+                .get(player)
+                .unwrap()
+                .dirty
+        );
+    }
+}
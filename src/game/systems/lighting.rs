@@ -0,0 +1,209 @@
+use super::super::{
+    components::{self, equipment},
+    fov,
+    resources::{game_map::GameMap, Light, LightMap},
+};
+use specs::{Read, ReadStorage, System, Write};
+
+/// Rebuilds the light map from every light source, including ones being
+/// carried in a light slot, and everything on fire.
+/// Light spreads out the same way sight does, so walls cast shadows and haze
+/// dims it.
+pub struct Lighting;
+
+/// How much of a light's intensity is left at the given distance from it
+fn falloff(light: &components::LightSource, distance: f32) -> f32 {
+    (light.intensity * (1. - distance / (light.radius + 1) as f32)).max(0.)
+}
+
+impl<'a> System<'a> for Lighting {
+    type SystemData = (
+        ReadStorage<'a, components::Position>,
+        ReadStorage<'a, components::LightSource>,
+        ReadStorage<'a, components::Burning>,
+        ReadStorage<'a, equipment::Equipment>,
+        Read<'a, GameMap>,
+        Write<'a, LightMap>,
+    );
+
+    fn run(
+        &mut self,
+        (positions, lights, burning, equipment, game_map, mut light_map): Self::SystemData,
+    ) {
+        use specs::Join;
+
+        light_map.tiles.clear();
+
+        let fire = components::Burning::light();
+        let fires = (&positions, &burning).join().map(|(pos, _)| (pos, &fire));
+
+        // Carried lights have no position of their own, so they shine from
+        // whoever is carrying them
+        let carried = (&positions, &equipment)
+            .join()
+            .filter_map(|(pos, equipment)| Some((pos, lights.get(equipment.light?)?)));
+
+        for (pos_light, light) in (&positions, &lights).join().chain(fires).chain(carried) {
+            for pos in fov::field_of_view(&game_map, pos_light, light.radius) {
+                // Smoke and the like soak up some of the light on the way
+                let strength = falloff(light, pos_light.distance_squared(&pos).sqrt())
+                    * fov::transmittance(&game_map, pos_light, &pos);
+
+                if strength <= 0. {
+                    continue;
+                }
+
+                let tile = light_map.tiles.entry(pos).or_insert_with(Light::default);
+
+                tile.r += strength * light.color.0 as f32 / 255.;
+                tile.g += strength * light.color.1 as f32 / 255.;
+                tile.b += strength * light.color.2 as f32 / 255.;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::pathfinding::test_map;
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world(rows: &[&str]) -> World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::LightSource>();
+        world.register::<components::Burning>();
+        world.register::<equipment::Equipment>();
+        world.insert(test_map::build(rows));
+        world.insert(LightMap::default());
+
+        world
+    }
+
+    fn add_light(world: &mut World, pos: components::Position, color: (u8, u8, u8)) {
+        world
+            .create_entity()
+            .with(pos)
+            .with(components::LightSource {
+                radius: 4,
+                color,
+                intensity: 1.,
+            })
+            .build();
+    }
+
+    fn light_up(world: &mut World) {
+        Lighting.run_now(world);
+        world.maintain();
+    }
+
+    #[test]
+    fn light_fades_with_distance() {
+        let mut world = build_world(&["........."]);
+
+        add_light(&mut world, components::Position::new(0, 0), (255, 255, 255));
+        light_up(&mut world);
+
+        let light_map = world.read_resource::<LightMap>();
+        let brightness = |x| {
+            light_map
+                .light_at(&components::Position::new(x, 0))
+                .map_or(0., |light| light.brightness())
+        };
+
+        assert_eq!(brightness(0), 1.);
+        assert!(brightness(1) > brightness(2));
+        assert!(brightness(2) > brightness(3));
+        assert!(light_map.is_lit(&components::Position::new(4, 0)));
+        assert!(!light_map.is_lit(&components::Position::new(5, 0)));
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        let mut world = build_world(&["..#.."]);
+
+        add_light(&mut world, components::Position::new(0, 0), (255, 255, 255));
+        light_up(&mut world);
+
+        let light_map = world.read_resource::<LightMap>();
+
+        assert!(light_map.is_lit(&components::Position::new(2, 0)));
+        assert!(!light_map.is_lit(&components::Position::new(3, 0)));
+    }
+
+    #[test]
+    fn lights_reach_past_wall_corners() {
+        let mut world = build_world(&["...", ".#.", "..."]);
+
+        add_light(&mut world, components::Position::new(0, 0), (255, 255, 255));
+        light_up(&mut world);
+
+        let light_map = world.read_resource::<LightMap>();
+
+        assert!(light_map.is_lit(&components::Position::new(2, 1)));
+    }
+
+    #[test]
+    fn lights_add_up() {
+        let mut world = build_world(&["..."]);
+        let middle = components::Position::new(1, 0);
+
+        add_light(&mut world, components::Position::new(0, 0), (255, 0, 0));
+        add_light(&mut world, components::Position::new(2, 0), (0, 0, 255));
+        light_up(&mut world);
+
+        let light_map = world.read_resource::<LightMap>();
+        let light = light_map.light_at(&middle).unwrap();
+
+        assert!(light.r > 0.);
+        assert_eq!(light.g, 0.);
+        assert_eq!(light.r, light.b);
+    }
+
+    #[test]
+    fn fires_give_off_light() {
+        let mut world = build_world(&["..."]);
+
+        world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::Burning { remaining: 1 })
+            .build();
+        light_up(&mut world);
+
+        let light_map = world.read_resource::<LightMap>();
+
+        assert!(light_map.is_lit(&components::Position::new(2, 0)));
+    }
+
+    #[test]
+    fn carried_lights_shine_from_their_carrier() {
+        let mut world = build_world(&["........."]);
+
+        let lantern = world
+            .create_entity()
+            .with(components::LightSource {
+                radius: 4,
+                color: (255, 255, 255),
+                intensity: 1.,
+            })
+            .build();
+
+        world
+            .create_entity()
+            .with(components::Position::new(8, 0))
+            .with(equipment::Equipment {
+                light: Some(lantern),
+                ..Default::default()
+            })
+            .build();
+        light_up(&mut world);
+
+        let light_map = world.read_resource::<LightMap>();
+
+        assert!(light_map.is_lit(&components::Position::new(5, 0)));
+        assert!(!light_map.is_lit(&components::Position::new(0, 0)));
+    }
+}
@@ -0,0 +1,220 @@
+use super::super::{
+    components::{self, equipment},
+    resources::{game_map::GameMap, DamageEvent, DamageEvents},
+};
+use specs::{Read, ReadStorage, System, Write, WriteStorage};
+
+/// Moving into a hostile creature attacks it instead of moving
+pub struct Melee;
+
+impl<'a> System<'a> for Melee {
+    type SystemData = (
+        specs::Entities<'a>,
+        WriteStorage<'a, components::Moved>,
+        ReadStorage<'a, components::Faction>,
+        ReadStorage<'a, components::Attack>,
+        ReadStorage<'a, components::Defense>,
+        ReadStorage<'a, components::Health>,
+        ReadStorage<'a, equipment::Equipment>,
+        ReadStorage<'a, equipment::Equippable>,
+        ReadStorage<'a, components::material::Material>,
+        Read<'a, GameMap>,
+        Write<'a, DamageEvents>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut moved,
+            factions,
+            attacks,
+            defenses,
+            healths,
+            equipment,
+            equippables,
+            materials,
+            game_map,
+            mut damage,
+        ): Self::SystemData,
+    ) {
+        use specs::Join;
+
+        let mut attacked = Vec::new();
+
+        for (entity, mv, faction, attack) in (&entities, &moved, &factions, &attacks).join() {
+            let target = game_map
+                .get_entities(&mv.to)
+                .into_iter()
+                .flatten()
+                .find(|other| {
+                    healths.contains(**other)
+                        && factions.get(**other).is_some_and(|other| other != faction)
+                });
+
+            if let Some(target) = target {
+                let power = attack.power
+                    + equipment::bonuses_of(entity, &equipment, &equippables, &materials).attack;
+                let armor = defenses.get(*target).map_or(0, |defense| defense.armor)
+                    + equipment::bonuses_of(*target, &equipment, &equippables, &materials).defense;
+
+                damage.pending.push(DamageEvent {
+                    target: *target,
+                    source: Some(entity),
+                    amount: (power - armor).max(0),
+                });
+
+                attacked.push(entity);
+            }
+        }
+
+        for entity in attacked {
+            moved.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Moved>();
+        world.register::<components::Faction>();
+        world.register::<components::Attack>();
+        world.register::<components::Defense>();
+        world.register::<components::Health>();
+        world.register::<equipment::Equipment>();
+        world.register::<equipment::Equippable>();
+        world.register::<components::material::Material>();
+
+        world.insert(GameMap::new());
+        world.insert(DamageEvents::default());
+
+        world
+    }
+
+    fn add_target(
+        world: &mut World,
+        pos: &components::Position,
+        faction: components::Faction,
+    ) -> specs::Entity {
+        let target = world
+            .create_entity()
+            .with(faction)
+            .with(components::Health::new(10))
+            .with(components::Defense { armor: 1 })
+            .build();
+
+        world.write_resource::<GameMap>().add(pos, target);
+
+        target
+    }
+
+    fn add_attacker(world: &mut World, to: &components::Position) -> specs::Entity {
+        world
+            .create_entity()
+            .with(components::Moved {
+                from: to.left(),
+                to: to.clone(),
+            })
+            .with(components::Faction::Player)
+            .with(components::Attack { power: 4 })
+            .build()
+    }
+
+    fn fight(world: &mut World) {
+        Melee.run_now(world);
+        world.maintain();
+    }
+
+    #[test]
+    fn bumping_an_enemy_attacks_it() {
+        let mut world = build_world();
+        let pos = components::Position::new(1, 0);
+        let target = add_target(&mut world, &pos, components::Faction::Monsters);
+        let attacker = add_attacker(&mut world, &pos);
+
+        fight(&mut world);
+
+        assert!(!world.read_storage::<components::Moved>().contains(attacker));
+        assert_eq!(
+            world.read_resource::<DamageEvents>().pending,
+            vec![DamageEvent {
+                target,
+                source: Some(attacker),
+                amount: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn leaves_friends_alone() {
+        let mut world = build_world();
+        let pos = components::Position::new(1, 0);
+
+        add_target(&mut world, &pos, components::Faction::Player);
+        let attacker = add_attacker(&mut world, &pos);
+
+        fight(&mut world);
+
+        assert!(world.read_storage::<components::Moved>().contains(attacker));
+        assert!(world.read_resource::<DamageEvents>().pending.is_empty());
+    }
+
+    #[test]
+    fn equipment_adds_to_attack_and_defense() {
+        let mut world = build_world();
+        let pos = components::Position::new(1, 0);
+        let target = add_target(&mut world, &pos, components::Faction::Monsters);
+        let attacker = add_attacker(&mut world, &pos);
+
+        let sword = world
+            .create_entity()
+            .with(equipment::Equippable {
+                slot: equipment::Slot::Weapon,
+                attack: 5,
+                vision: 0,
+            })
+            .build();
+        let mail = world
+            .create_entity()
+            .with(equipment::Equippable {
+                slot: equipment::Slot::Armor,
+                attack: 0,
+                vision: 0,
+            })
+            .with(components::material::iron())
+            .build();
+
+        let mut equipment = world.write_storage::<equipment::Equipment>();
+
+        equipment
+            .insert(
+                attacker,
+                equipment::Equipment {
+                    weapon: Some(sword),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        equipment
+            .insert(
+                target,
+                equipment::Equipment {
+                    armor: Some(mail),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        drop(equipment);
+
+        fight(&mut world);
+
+        // 4 + 5 power against 1 + 3 armor
+        assert_eq!(world.read_resource::<DamageEvents>().pending[0].amount, 5);
+    }
+}
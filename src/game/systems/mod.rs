@@ -1,6 +1,17 @@
+pub mod camera;
 pub mod collisions_solid;
+pub mod damage;
+pub mod fire;
+pub mod fluid_drag;
+pub mod fluid_spread;
+pub mod items;
+pub mod lighting;
+pub mod melee;
+pub mod monster_ai;
 pub mod movement_apply;
+pub mod player_flow_field;
 pub mod player_input;
 pub mod render;
+pub mod scheduler;
 pub mod sync_game_map;
 pub mod visibility;
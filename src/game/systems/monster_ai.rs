@@ -0,0 +1,318 @@
+use super::super::{
+    components::{self, Behavior},
+    pathfinding::{self, Movement},
+    resources::{
+        self,
+        game_map::{GameMap, TileProperties},
+    },
+};
+use rand::seq::SliceRandom;
+use specs::{Read, ReadStorage, System, Write, WriteStorage};
+
+pub struct MonsterAi;
+
+impl<'a> System<'a> for MonsterAi {
+    type SystemData = (
+        ReadStorage<'a, components::Ai>,
+        ReadStorage<'a, components::Position>,
+        ReadStorage<'a, components::Viewshed>,
+        Option<Read<'a, resources::Player>>,
+        WriteStorage<'a, components::Moved>,
+        WriteStorage<'a, components::Energy>,
+        Read<'a, resources::Turn>,
+        Read<'a, resources::PlayerFlowField>,
+        Read<'a, GameMap>,
+        Write<'a, resources::GameRng>,
+        Read<'a, specs::LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            ai,
+            position,
+            viewsheds,
+            player,
+            mut moved,
+            mut energy,
+            turn,
+            flow_field,
+            game_map,
+            mut rng,
+            updater,
+        ): Self::SystemData,
+    ) {
+        let pos_player = player.and_then(|player| position.get(player.ent));
+
+        for entity in turn.ready.iter().cloned() {
+            let (ai, pos) = match (ai.get(entity), position.get(entity)) {
+                (Some(ai), Some(pos)) => (ai, pos),
+                _ => continue,
+            };
+
+            // Monsters only react to the player once they can actually see them
+            let sees_player = match (viewsheds.get(entity), pos_player) {
+                (Some(viewshed), Some(pos_player)) => viewshed.can_see(pos_player),
+                _ => false,
+            };
+
+            // Standing around still takes a turn
+            if let Some(energy) = energy.get_mut(entity) {
+                energy.spend();
+            }
+
+            let target = match ai.behavior {
+                Behavior::Idle => None,
+
+                // Wander off somewhere nobody else is standing
+                Behavior::Wander => pathfinding::neighbors(&game_map, pos, Movement::FourWay)
+                    .into_iter()
+                    .map(|(next, _)| next)
+                    .filter(|next| !game_map.tile_is(next, TileProperties::OCCUPIED))
+                    .collect::<Vec<_>>()
+                    .choose(&mut rng.0)
+                    .cloned(),
+
+                // Walking into the player once next to them attacks them
+                Behavior::Chase if sees_player => {
+                    flow_field.toward.step_from(pos, Movement::EightWay)
+                }
+
+                Behavior::Flee if sees_player => flow_field.away.step_from(pos, Movement::EightWay),
+
+                Behavior::Chase | Behavior::Flee => None,
+            };
+
+            let target = match target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            if let Some(mv) = moved.get_mut(entity) {
+                mv.to = target;
+            } else {
+                let mv = components::Moved {
+                    from: pos.clone(),
+                    to: target,
+                };
+
+                updater.insert(entity, mv);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::pathfinding::test_map;
+    use super::super::player_flow_field::PlayerFlowField;
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Ai>();
+        world.register::<components::Position>();
+        world.register::<components::Viewshed>();
+        world.register::<components::Moved>();
+        world.register::<components::Energy>();
+
+        world.insert(test_map::build(&[
+            ".........", //
+            ".........", //
+            ".........", //
+        ]));
+        world.insert(resources::PlayerFlowField::default());
+        world.insert(resources::GameRng::default());
+        world.insert(resources::Turn::default());
+
+        let ent_player = world
+            .create_entity()
+            .with(components::Position::new(1, 1))
+            .build();
+
+        world.insert(resources::Player { ent: ent_player });
+
+        PlayerFlowField.run_now(&world);
+        world.maintain();
+
+        world
+    }
+
+    fn spawn(
+        world: &mut World,
+        pos: components::Position,
+        behavior: Behavior,
+        sees_player: bool,
+    ) -> specs::Entity {
+        let mut viewshed = components::Viewshed::new(10);
+
+        if sees_player {
+            viewshed.visible.insert(components::Position::new(1, 1));
+        }
+
+        let builder = world
+            .create_entity()
+            .with(pos)
+            .with(components::Ai { behavior })
+            .with(components::Energy {
+                value: components::ACTION_COST,
+                speed: components::NORMAL_SPEED,
+            });
+
+        let ent = builder.with(viewshed).build();
+
+        world.write_resource::<resources::Turn>().ready.push(ent);
+
+        ent
+    }
+
+    fn destination(world: &World, ent: specs::Entity) -> Option<components::Position> {
+        world
+            .read_storage::<components::Moved>()
+            .get(ent)
+            .map(|mv| mv.to.clone())
+    }
+
+    fn take_turn(world: &mut World) {
+        MonsterAi.run_now(world);
+        world.maintain();
+    }
+
+    #[test]
+    fn idle_stays_put() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(5, 1),
+            Behavior::Idle,
+            true,
+        );
+
+        take_turn(&mut world);
+
+        assert_eq!(destination(&world, ent), None);
+    }
+
+    #[test]
+    fn wanders_to_a_neighbor() {
+        let mut world = build_world();
+        let start = components::Position::new(5, 1);
+        let ent = spawn(&mut world, start.clone(), Behavior::Wander, false);
+
+        take_turn(&mut world);
+
+        let to = destination(&world, ent).expect("Wanderer should have moved");
+
+        assert!(
+            [start.up(), start.down(), start.left(), start.right()].contains(&to),
+            "Wandered to {:?}",
+            to
+        );
+    }
+
+    #[test]
+    fn chases_when_player_in_view() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(5, 1),
+            Behavior::Chase,
+            true,
+        );
+
+        take_turn(&mut world);
+
+        assert_eq!(
+            destination(&world, ent),
+            Some(components::Position::new(4, 1))
+        );
+    }
+
+    #[test]
+    fn doesnt_chase_when_player_out_of_view() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(5, 1),
+            Behavior::Chase,
+            false,
+        );
+
+        take_turn(&mut world);
+
+        assert_eq!(destination(&world, ent), None);
+    }
+
+    #[test]
+    fn chaser_attacks_player_next_to_it() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(2, 1),
+            Behavior::Chase,
+            true,
+        );
+
+        take_turn(&mut world);
+
+        assert_eq!(
+            destination(&world, ent),
+            Some(components::Position::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn flees_when_player_in_view() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(5, 1),
+            Behavior::Flee,
+            true,
+        );
+
+        take_turn(&mut world);
+
+        let to = destination(&world, ent).expect("Should have run away");
+
+        assert_eq!(to.x, 6);
+    }
+
+    #[test]
+    fn only_ready_monsters_act() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(5, 1),
+            Behavior::Chase,
+            true,
+        );
+
+        world.write_resource::<resources::Turn>().ready.clear();
+
+        take_turn(&mut world);
+
+        assert_eq!(destination(&world, ent), None);
+    }
+
+    #[test]
+    fn spends_energy_even_when_idle() {
+        let mut world = build_world();
+        let ent = spawn(
+            &mut world,
+            components::Position::new(5, 1),
+            Behavior::Idle,
+            true,
+        );
+
+        take_turn(&mut world);
+
+        assert!(!world
+            .read_storage::<components::Energy>()
+            .get(ent)
+            .unwrap()
+            .is_ready());
+    }
+}
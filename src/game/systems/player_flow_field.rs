@@ -0,0 +1,117 @@
+use super::super::{
+    components,
+    pathfinding::{DijkstraMap, Movement, STRAIGHT_COST},
+    resources::{self, game_map::GameMap},
+};
+use specs::{Read, ReadStorage, System, Write};
+
+/// How far out from the player the flow field reaches, in straight steps
+pub const FLOW_FIELD_RANGE: u32 = 60;
+
+pub struct PlayerFlowField;
+
+impl<'a> System<'a> for PlayerFlowField {
+    type SystemData = (
+        Option<Read<'a, resources::Player>>,
+        ReadStorage<'a, components::Position>,
+        Read<'a, GameMap>,
+        Write<'a, resources::PlayerFlowField>,
+    );
+
+    fn run(&mut self, (player, positions, game_map, mut flow_field): Self::SystemData) {
+        let pos_player = match player.and_then(|player| positions.get(player.ent)) {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        if flow_field.origin.as_ref() == Some(pos_player) {
+            return;
+        }
+
+        let toward = DijkstraMap::new(
+            &game_map,
+            std::slice::from_ref(pos_player),
+            Movement::EightWay,
+            FLOW_FIELD_RANGE * STRAIGHT_COST,
+        );
+
+        flow_field.away = toward.inverted(&game_map, Movement::EightWay);
+        flow_field.toward = toward;
+        flow_field.origin = Some(pos_player.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::pathfinding::test_map;
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world(start: components::Position) -> (specs::World, specs::Entity) {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.insert(test_map::build(&[
+            ".......", //
+            ".......", //
+            ".......", //
+        ]));
+        world.insert(resources::PlayerFlowField::default());
+
+        let ent = world.create_entity().with(start).build();
+
+        world.insert(resources::Player { ent });
+
+        (world, ent)
+    }
+
+    #[test]
+    fn builds_maps_around_player() {
+        let start = components::Position::new(3, 1);
+        let (mut world, _) = build_world(start.clone());
+
+        PlayerFlowField.run_now(&world);
+        world.maintain();
+
+        let flow_field = world.read_resource::<resources::PlayerFlowField>();
+
+        assert_eq!(flow_field.origin, Some(start.clone()));
+        assert_eq!(flow_field.toward.cost(&start), Some(0));
+        assert_eq!(
+            flow_field
+                .toward
+                .step_from(&components::Position::new(0, 1), Movement::EightWay),
+            Some(components::Position::new(1, 1))
+        );
+        assert_eq!(
+            flow_field
+                .away
+                .step_from(&components::Position::new(4, 1), Movement::FourWay),
+            Some(components::Position::new(5, 1))
+        );
+    }
+
+    #[test]
+    fn rebuilds_when_player_moves() {
+        let start = components::Position::new(3, 1);
+        let target = start.right();
+        let (mut world, ent) = build_world(start);
+
+        PlayerFlowField.run_now(&world);
+        world.maintain();
+
+        world
+            .write_storage::<components::Position>()
+            .get_mut(ent)
+            .unwrap()
+            .set(&target);
+
+        PlayerFlowField.run_now(&world);
+        world.maintain();
+
+        let flow_field = world.read_resource::<resources::PlayerFlowField>();
+
+        assert_eq!(flow_field.origin, Some(target.clone()));
+        assert_eq!(flow_field.toward.cost(&target), Some(0));
+    }
+}
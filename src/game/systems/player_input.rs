@@ -8,6 +8,7 @@ impl<'a> System<'a> for PlayerInput {
         ReadStorage<'a, components::Player>,
         ReadStorage<'a, components::Position>,
         WriteStorage<'a, components::Moved>,
+        WriteStorage<'a, components::Energy>,
         Read<'a, resources::PendingAction>,
         specs::Entities<'a>,
         Read<'a, specs::LazyUpdate>,
@@ -15,12 +16,20 @@ impl<'a> System<'a> for PlayerInput {
 
     fn run(
         &mut self,
-        (player, position, mut moved, pending_action, entities, updater): Self::SystemData,
+        (player, position, mut moved, mut energy, pending_action, entities, updater): Self::SystemData,
     ) {
         use specs::Join;
 
         let pending_action = &pending_action.0;
 
+        // A player with energy has to wait for their turn
+        if (&player, &energy)
+            .join()
+            .any(|(_, energy)| !energy.is_ready())
+        {
+            return;
+        }
+
         match pending_action {
             Some(input::Action::Up) => {
                 for (entity, pos, _, mv) in
@@ -94,6 +103,20 @@ impl<'a> System<'a> for PlayerInput {
 
             None => (),
         };
+
+        let takes_turn = matches!(
+            pending_action,
+            Some(input::Action::Up)
+                | Some(input::Action::Down)
+                | Some(input::Action::Left)
+                | Some(input::Action::Right)
+        );
+
+        if takes_turn {
+            for (_, energy) in (&player, &mut energy).join() {
+                energy.spend();
+            }
+        }
     }
 }
 
@@ -112,6 +135,7 @@ mod tests {
         world.register::<components::Player>();
         world.register::<components::Position>();
         world.register::<components::Moved>();
+        world.register::<components::Energy>();
 
         world.insert(resources::PendingAction(pending_action));
 
@@ -206,4 +230,69 @@ mod tests {
             components::Position::new(4, -3),
         );
     }
+
+    #[test]
+    fn spends_energy_to_move() {
+        let mut world = World::new();
+
+        world.register::<components::Player>();
+        world.register::<components::Position>();
+        world.register::<components::Moved>();
+        world.register::<components::Energy>();
+
+        world.insert(resources::PendingAction(Some(input::Action::Up)));
+
+        let ent_player = world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::Player)
+            .with(components::Energy {
+                value: components::ACTION_COST,
+                speed: components::NORMAL_SPEED,
+            })
+            .build();
+
+        PlayerInput.run_now(&world);
+        world.maintain();
+
+        assert!(world
+            .read_storage::<components::Moved>()
+            .get(ent_player)
+            .is_some());
+        assert_eq!(
+            world
+                .read_storage::<components::Energy>()
+                .get(ent_player)
+                .unwrap()
+                .value,
+            0
+        );
+    }
+
+    #[test]
+    fn waits_for_turn_without_energy() {
+        let mut world = World::new();
+
+        world.register::<components::Player>();
+        world.register::<components::Position>();
+        world.register::<components::Moved>();
+        world.register::<components::Energy>();
+
+        world.insert(resources::PendingAction(Some(input::Action::Up)));
+
+        let ent_player = world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::Player)
+            .with(components::Energy::new(components::NORMAL_SPEED))
+            .build();
+
+        PlayerInput.run_now(&world);
+        world.maintain();
+
+        assert!(world
+            .read_storage::<components::Moved>()
+            .get(ent_player)
+            .is_none());
+    }
 }
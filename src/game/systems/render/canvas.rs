@@ -0,0 +1,203 @@
+use super::super::super::components::{self, Sprite};
+use super::layout::Rect;
+
+/// Everything that will be on screen once this frame is done
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Sprite>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize, fill: Sprite) -> Frame {
+        Frame {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Sprite> {
+        if x < self.width && y < self.height {
+            self.cells.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// A canvas for drawing inside the given part of the frame only
+    pub fn canvas(&mut self, rect: Rect) -> Canvas<'_> {
+        // Never reach outside the frame itself either
+        let right = (rect.x + rect.width).min(self.width);
+        let bottom = (rect.y + rect.height).min(self.height);
+        let rect = Rect::new(
+            rect.x,
+            rect.y,
+            right.saturating_sub(rect.x),
+            bottom.saturating_sub(rect.y),
+        );
+
+        Canvas { frame: self, rect }
+    }
+}
+
+/// A single character of interface text on a black background
+pub fn text_sprite(rune: char, color: (u8, u8, u8)) -> Sprite {
+    text_sprite_on(rune, color, (0, 0, 0))
+}
+
+/// A single character of interface text on a colored background
+pub fn text_sprite_on(rune: char, fg: (u8, u8, u8), bg: (u8, u8, u8)) -> Sprite {
+    Sprite {
+        fg_r: fg.0,
+        fg_g: fg.1,
+        fg_b: fg.2,
+
+        bg_r: bg.0,
+        bg_g: bg.1,
+        bg_b: bg.2,
+
+        layer: components::DL_FLOOR,
+
+        rune,
+    }
+}
+
+/// Draws into one panel of a frame.  Coordinates are relative to the panel's
+/// top left corner and anything outside of it is clipped.
+pub struct Canvas<'f> {
+    frame: &'f mut Frame,
+    rect: Rect,
+}
+
+impl<'f> Canvas<'f> {
+    pub fn width(&self) -> usize {
+        self.rect.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rect.height
+    }
+
+    /// A canvas for part of this one, given relative to it and clipped to it
+    pub fn area(&mut self, rect: Rect) -> Canvas<'_> {
+        let x = rect.x.min(self.rect.width);
+        let y = rect.y.min(self.rect.height);
+        let rect = Rect::new(
+            self.rect.x + x,
+            self.rect.y + y,
+            rect.width.min(self.rect.width - x),
+            rect.height.min(self.rect.height - y),
+        );
+
+        Canvas {
+            frame: &mut *self.frame,
+            rect,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Sprite> {
+        if x >= self.rect.width || y >= self.rect.height {
+            return None;
+        }
+
+        self.frame.get(self.rect.x + x, self.rect.y + y).cloned()
+    }
+
+    pub fn put(&mut self, x: usize, y: usize, sprite: Sprite) {
+        if x >= self.rect.width || y >= self.rect.height {
+            return;
+        }
+
+        let i = (self.rect.y + y) * self.frame.width + self.rect.x + x;
+
+        self.frame.cells[i] = sprite;
+    }
+
+    pub fn fill(&mut self, sprite: &Sprite) {
+        for y in 0..self.rect.height {
+            for x in 0..self.rect.width {
+                self.put(x, y, sprite.clone());
+            }
+        }
+    }
+
+    /// Writes a line of text, cut off at the edge of the panel
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: (u8, u8, u8)) {
+        self.draw_text_on(x, y, text, color, (0, 0, 0));
+    }
+
+    pub fn draw_text_on(
+        &mut self,
+        x: usize,
+        y: usize,
+        text: &str,
+        fg: (u8, u8, u8),
+        bg: (u8, u8, u8),
+    ) {
+        for (i, rune) in text.chars().enumerate() {
+            self.put(x + i, y, text_sprite_on(rune, fg, bg));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runes(frame: &Frame) -> Vec<String> {
+        (0..frame.height)
+            .map(|y| {
+                (0..frame.width)
+                    .map(|x| frame.get(x, y).unwrap().rune)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn draws_relative_to_the_panel() {
+        let mut frame = Frame::new(4, 3, text_sprite('.', (0, 0, 0)));
+
+        frame
+            .canvas(Rect::new(1, 1, 2, 2))
+            .put(1, 0, text_sprite('x', (0, 0, 0)));
+
+        assert_eq!(runes(&frame), vec!["....", "..x.", "...."]);
+    }
+
+    #[test]
+    fn clips_to_the_panel() {
+        let mut frame = Frame::new(5, 3, text_sprite('.', (0, 0, 0)));
+        let mut canvas = frame.canvas(Rect::new(1, 1, 3, 1));
+
+        canvas.draw_text(1, 0, "hello", (255, 255, 255));
+        canvas.draw_text(0, 1, "below", (255, 255, 255));
+
+        assert_eq!(runes(&frame), vec![".....", "..he.", "....."]);
+    }
+
+    #[test]
+    fn panels_hanging_off_the_frame_are_cut_short() {
+        let mut frame = Frame::new(3, 2, text_sprite('.', (0, 0, 0)));
+        let mut canvas = frame.canvas(Rect::new(1, 1, 10, 10));
+
+        assert_eq!((canvas.width(), canvas.height()), (2, 1));
+
+        canvas.fill(&text_sprite('#', (0, 0, 0)));
+
+        assert_eq!(runes(&frame), vec!["...", ".##"]);
+    }
+
+    #[test]
+    fn areas_stay_inside_their_canvas() {
+        let mut frame = Frame::new(5, 3, text_sprite('.', (0, 0, 0)));
+        let mut canvas = frame.canvas(Rect::new(1, 0, 3, 3));
+
+        canvas
+            .area(Rect::new(1, 1, 10, 1))
+            .draw_text(0, 0, "wxyz", (0, 0, 0));
+
+        assert_eq!(runes(&frame), vec![".....", "..wx.", "....."]);
+    }
+}
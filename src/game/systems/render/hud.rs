@@ -0,0 +1,200 @@
+use super::super::super::resources::game_log::GameLog;
+use super::canvas::{text_sprite, Canvas};
+use super::layout::Rect;
+use super::widgets::{self, Line, ListState};
+
+const DIVIDER: (u8, u8, u8) = (90, 90, 90);
+const LABEL: (u8, u8, u8) = (160, 160, 160);
+const VALUE: (u8, u8, u8) = (230, 230, 230);
+const DANGER: (u8, u8, u8) = (255, 90, 80);
+const HEALTHY: (u8, u8, u8) = (80, 200, 80);
+const EMPTY: (u8, u8, u8) = (50, 50, 50);
+
+/// What the sidebar shows about the player
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    pub health: Option<(i32, i32)>,
+    pub attack: Option<i32>,
+    pub defense: Option<i32>,
+    pub time: u64,
+
+    /// What's in each equipment slot, for anything that has them
+    pub equipment: Vec<(&'static str, Option<String>)>,
+}
+
+/// A dividing line along the top, followed by the latest messages
+pub fn draw_log(canvas: &mut Canvas, log: &GameLog) {
+    canvas.fill(&text_sprite(' ', DIVIDER));
+
+    for x in 0..canvas.width() {
+        canvas.put(x, 0, text_sprite('─', DIVIDER));
+    }
+
+    if log.scroll() > 0 {
+        canvas.draw_text(2, 0, &format!(" {} newer ", log.scroll()), DIVIDER);
+    }
+
+    let rows = canvas.height().saturating_sub(1);
+
+    for (i, message) in log.visible(rows).iter().enumerate() {
+        canvas.draw_text(0, i + 1, &message.text, message.color);
+    }
+}
+
+/// A dividing line down the left, followed by the player's stats
+pub fn draw_sidebar(canvas: &mut Canvas, status: &Status) {
+    canvas.fill(&text_sprite(' ', DIVIDER));
+
+    for y in 0..canvas.height() {
+        canvas.put(0, y, text_sprite('│', DIVIDER));
+    }
+
+    let mut row = 1;
+
+    if let Some((current, max)) = status.health {
+        let color = if current * 3 <= max { DANGER } else { VALUE };
+
+        canvas.draw_text(2, row, "Health", LABEL);
+        canvas.draw_text(10, row, &format!("{}/{}", current.max(0), max), color);
+
+        let bar_color = if color == DANGER { DANGER } else { HEALTHY };
+        let bar_width = canvas.width().saturating_sub(4);
+
+        widgets::draw_progress_bar(
+            canvas,
+            2,
+            row + 1,
+            bar_width,
+            (current, max),
+            bar_color,
+            EMPTY,
+        );
+        row += 3;
+    }
+
+    if let Some(attack) = status.attack {
+        canvas.draw_text(2, row, "Attack", LABEL);
+        canvas.draw_text(10, row, &attack.to_string(), VALUE);
+        row += 1;
+    }
+
+    if let Some(defense) = status.defense {
+        canvas.draw_text(2, row, "Defense", LABEL);
+        canvas.draw_text(10, row, &defense.to_string(), VALUE);
+        row += 1;
+    }
+
+    canvas.draw_text(2, row + 1, "Time", LABEL);
+    canvas.draw_text(10, row + 1, &status.time.to_string(), VALUE);
+    row += 3;
+
+    // Item names can be long, so each gets a row of its own under its slot
+    for (slot, item) in &status.equipment {
+        canvas.draw_text(2, row, slot, LABEL);
+
+        match item {
+            Some(item) => canvas.draw_text(3, row + 1, item, VALUE),
+            None => canvas.draw_text(3, row + 1, "-", EMPTY),
+        }
+
+        row += 2;
+    }
+}
+
+/// Lists what the player is carrying in a box, with the selected item picked
+/// out and a reminder of the keys along the bottom
+pub fn draw_inventory(canvas: &mut Canvas, items: &[Line], selected: usize) {
+    let inner = widgets::draw_box(canvas, Some("Inventory"), VALUE);
+    let mut inside = canvas.area(inner);
+    let width = inside.width();
+    let rows = inside.height().saturating_sub(2);
+
+    if items.is_empty() {
+        inside.draw_text(0, 0, "You aren't carrying anything.", LABEL);
+    } else {
+        let mut state = ListState {
+            selected,
+            offset: 0,
+        };
+
+        widgets::draw_menu(
+            &mut inside.area(Rect::new(0, 0, width, rows)),
+            items,
+            &mut state,
+        );
+    }
+
+    let bottom = inside.height().saturating_sub(1);
+
+    inside.draw_text(0, bottom, "u use  d drop  e equip  r remove", LABEL);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{canvas::Frame, layout::Rect};
+    use super::*;
+
+    fn row(frame: &Frame, y: usize) -> String {
+        (0..frame.width)
+            .map(|x| frame.get(x, y).unwrap().rune)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn sidebar_shows_equipment_by_slot() {
+        let mut frame = Frame::new(20, 10, text_sprite(' ', (0, 0, 0)));
+        let status = Status {
+            equipment: vec![("Weapon", Some(String::from("sword"))), ("Armor", None)],
+            ..Status::default()
+        };
+
+        draw_sidebar(&mut frame.canvas(Rect::new(0, 0, 20, 10)), &status);
+
+        assert_eq!(row(&frame, 4), "│ Weapon");
+        assert_eq!(row(&frame, 5), "│  sword");
+        assert_eq!(row(&frame, 6), "│ Armor");
+        assert_eq!(row(&frame, 7), "│  -");
+    }
+
+    #[test]
+    fn inventory_lists_items_with_the_pick_inverted() {
+        let mut frame = Frame::new(34, 7, text_sprite(' ', (0, 0, 0)));
+        let items = vec![
+            Line::new("potion", VALUE),
+            Line::new("another potion", VALUE),
+        ];
+
+        draw_inventory(&mut frame.canvas(Rect::new(0, 0, 34, 7)), &items, 1);
+
+        assert_eq!(row(&frame, 0), "┌─ Inventory ────────────────────┐");
+        assert_eq!(row(&frame, 1), "│potion                          │");
+        assert_eq!(row(&frame, 2), "│another potion                  │");
+        assert_eq!(row(&frame, 5), "│u use  d drop  e equip  r remove│");
+        assert_eq!(frame.get(1, 2).unwrap().bg_r, VALUE.0);
+        assert_eq!(frame.get(1, 1).unwrap().bg_r, 0);
+    }
+
+    #[test]
+    fn sidebar_shows_player_stats() {
+        let mut frame = Frame::new(20, 8, text_sprite(' ', (0, 0, 0)));
+        let status = Status {
+            health: Some((7, 30)),
+            attack: Some(5),
+            defense: None,
+            time: 42,
+            equipment: Vec::new(),
+        };
+
+        draw_sidebar(&mut frame.canvas(Rect::new(0, 0, 20, 8)), &status);
+
+        assert_eq!(row(&frame, 1), "│ Health  7/30");
+        assert_eq!(row(&frame, 2), "│ ████████████████");
+        assert_eq!(row(&frame, 4), "│ Attack  5");
+        assert_eq!(row(&frame, 6), "│ Time    42");
+        assert_eq!(frame.get(10, 1).unwrap().fg_r, DANGER.0);
+        assert_eq!(frame.get(2, 2).unwrap().fg_r, DANGER.0);
+        assert_eq!(frame.get(17, 2).unwrap().fg_r, EMPTY.0);
+    }
+}
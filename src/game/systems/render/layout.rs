@@ -0,0 +1,178 @@
+/// Rows at the bottom of the screen kept for the message log, not counting the
+/// line that divides it from the rest
+pub const LOG_ROWS: usize = 5;
+
+/// Columns on the right of the screen kept for the status sidebar, including
+/// its dividing line
+pub const SIDEBAR_WIDTH: usize = 20;
+
+/// The sidebar is left out rather than squeeze the map narrower than this
+pub const MIN_MAP_WIDTH: usize = 20;
+
+/// A rectangle of screen cells, with the top left corner at 0, 0
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Cuts off the given number of rows at the bottom, as much as fits
+    pub fn split_bottom(&self, rows: usize) -> (Rect, Rect) {
+        let rows = rows.min(self.height);
+        let top = self.height - rows;
+
+        (
+            Rect::new(self.x, self.y, self.width, top),
+            Rect::new(self.x, self.y + top, self.width, rows),
+        )
+    }
+
+    /// Cuts off the given number of columns on the right, as much as fits
+    pub fn split_right(&self, columns: usize) -> (Rect, Rect) {
+        let columns = columns.min(self.width);
+        let left = self.width - columns;
+
+        (
+            Rect::new(self.x, self.y, left, self.height),
+            Rect::new(self.x + left, self.y, columns, self.height),
+        )
+    }
+
+    /// A rectangle of the given size in the middle of this one, shrunk to fit
+    pub fn centered(&self, width: usize, height: usize) -> Rect {
+        let width = width.min(self.width);
+        let height = height.min(self.height);
+
+        Rect::new(
+            self.x + (self.width - width) / 2,
+            self.y + (self.height - height) / 2,
+            width,
+            height,
+        )
+    }
+}
+
+/// Where each panel goes on the screen.  Panels never overlap, except for
+/// modal overlays which are drawn on top of the map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub screen: Rect,
+    pub map: Rect,
+    pub sidebar: Option<Rect>,
+    pub log: Option<Rect>,
+}
+
+impl Layout {
+    /// Splits the screen between the map and whichever panels are wanted,
+    /// leaving out any that there isn't room for
+    pub fn new(width: usize, height: usize, log: bool, sidebar: bool) -> Layout {
+        let screen = Rect::new(0, 0, width, height);
+
+        let (rest, log) = if log && height > 0 {
+            let (rest, log) = screen.split_bottom(LOG_ROWS + 1);
+
+            (rest, Some(log))
+        } else {
+            (screen, None)
+        };
+
+        let (map, sidebar) = if sidebar && rest.width >= SIDEBAR_WIDTH + MIN_MAP_WIDTH {
+            let (map, sidebar) = rest.split_right(SIDEBAR_WIDTH);
+
+            (map, Some(sidebar))
+        } else {
+            (rest, None)
+        };
+
+        Layout {
+            screen,
+            map,
+            sidebar,
+            log,
+        }
+    }
+
+    /// Where a modal window of the given size goes, over the middle of the map
+    pub fn modal(&self, width: usize, height: usize) -> Rect {
+        self.map.centered(width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_gets_whole_screen_without_panels() {
+        let layout = Layout::new(80, 24, false, false);
+
+        assert_eq!(layout.map, Rect::new(0, 0, 80, 24));
+        assert_eq!(layout.sidebar, None);
+        assert_eq!(layout.log, None);
+    }
+
+    #[test]
+    fn panels_share_the_screen_without_overlapping() {
+        let layout = Layout::new(80, 24, true, true);
+        let log = layout.log.unwrap();
+        let sidebar = layout.sidebar.unwrap();
+
+        assert_eq!(log, Rect::new(0, 24 - LOG_ROWS - 1, 80, LOG_ROWS + 1));
+        assert_eq!(
+            sidebar,
+            Rect::new(80 - SIDEBAR_WIDTH, 0, SIDEBAR_WIDTH, log.y)
+        );
+        assert_eq!(layout.map, Rect::new(0, 0, sidebar.x, log.y));
+    }
+
+    #[test]
+    fn leaves_out_sidebar_on_narrow_screens() {
+        let layout = Layout::new(SIDEBAR_WIDTH + MIN_MAP_WIDTH - 1, 24, true, true);
+
+        assert_eq!(layout.sidebar, None);
+        assert_eq!(layout.map.width, SIDEBAR_WIDTH + MIN_MAP_WIDTH - 1);
+    }
+
+    #[test]
+    fn log_shrinks_to_fit_tiny_screens() {
+        let layout = Layout::new(10, 3, true, false);
+
+        assert_eq!(layout.log, Some(Rect::new(0, 0, 10, 3)));
+        assert!(layout.map.is_empty());
+    }
+
+    #[test]
+    fn modals_are_centered_over_the_map() {
+        let layout = Layout::new(80, 24, true, true);
+        let modal = layout.modal(20, 10);
+
+        assert!(layout.map.contains(modal.x, modal.y));
+        assert!(layout
+            .map
+            .contains(modal.x + modal.width - 1, modal.y + modal.height - 1));
+        assert_eq!(
+            modal.x - layout.map.x,
+            layout.map.width - modal.x - modal.width
+        );
+    }
+}
@@ -0,0 +1,809 @@
+use super::super::{
+    components::{self, equipment},
+    resources::{self, game_log::GameLog},
+};
+use specs::{Read, ReadStorage, System, Write};
+
+pub mod canvas;
+pub mod hud;
+pub mod layout;
+pub mod widgets;
+
+use canvas::Frame;
+use layout::Layout;
+
+/// Size of the inventory window, shrunk to fit smaller maps
+const INVENTORY_WIDTH: usize = 36;
+const INVENTORY_HEIGHT: usize = 14;
+
+#[derive(Clone, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Self) -> bool {
+        self.r == other.r && self.g == other.g && self.b == other.b
+    }
+}
+
+pub trait Renderer {
+    fn draw_at(&mut self, x: usize, y: usize, fg: &Color, bg: &Color, rune: char);
+
+    fn size(&self) -> (usize, usize);
+
+    fn flush(&mut self);
+}
+
+pub struct Render<T: Renderer> {
+    renderer: T,
+
+    back_buffer: Vec<components::Sprite>,
+    back_buffer_width: usize,
+    back_buffer_height: usize,
+}
+
+impl<T: Renderer> Render<T> {
+    pub fn new(renderer: T) -> Render<T> {
+        Render {
+            renderer,
+            back_buffer: vec![],
+            back_buffer_width: 0,
+            back_buffer_height: 0,
+        }
+    }
+}
+
+/// Whether something stays put and is worth remembering once out of sight
+fn is_terrain(shape: Option<&components::Shape>) -> bool {
+    matches!(
+        shape,
+        Some(components::Shape::Floor) | Some(components::Shape::FullBlock)
+    )
+}
+
+/// Washes out and darkens a sprite so remembered tiles stand apart from what's
+/// actually in view
+fn remembered(sprite: &components::Sprite) -> components::Sprite {
+    let fade = |r: u8, g: u8, b: u8| {
+        let gray = (r as u32 * 3 + g as u32 * 6 + b as u32) / 10;
+
+        (
+            ((r as u32 + gray) / 4) as u8,
+            ((g as u32 + gray) / 4) as u8,
+            ((b as u32 + gray) / 4) as u8,
+        )
+    };
+
+    let (fg_r, fg_g, fg_b) = fade(sprite.fg_r, sprite.fg_g, sprite.fg_b);
+    let (bg_r, bg_g, bg_b) = fade(sprite.bg_r, sprite.bg_g, sprite.bg_b);
+
+    components::Sprite {
+        fg_r,
+        fg_g,
+        fg_b,
+
+        bg_r,
+        bg_g,
+        bg_b,
+
+        layer: sprite.layer.clone(),
+
+        rune: sprite.rune,
+    }
+}
+
+/// Even the dimmest visible tile keeps this much of its color so it can still
+/// be made out
+const MIN_TINT: f32 = 0.25;
+
+/// Colors a sprite by the light falling on it
+fn tinted(sprite: &components::Sprite, light: &resources::Light) -> components::Sprite {
+    let tint = |channel: u8, light: f32| {
+        (channel as f32 * (MIN_TINT + (1. - MIN_TINT) * light.min(1.))) as u8
+    };
+
+    components::Sprite {
+        fg_r: tint(sprite.fg_r, light.r),
+        fg_g: tint(sprite.fg_g, light.g),
+        fg_b: tint(sprite.fg_b, light.b),
+
+        bg_r: tint(sprite.bg_r, light.r),
+        bg_g: tint(sprite.bg_g, light.g),
+        bg_b: tint(sprite.bg_b, light.b),
+
+        layer: sprite.layer.clone(),
+
+        rune: sprite.rune,
+    }
+}
+
+impl<'a, T: Renderer> System<'a> for Render<T> {
+    type SystemData = (
+        Read<'a, resources::CameraCenter>,
+        Option<Read<'a, resources::Player>>,
+        Option<Read<'a, resources::LightMap>>,
+        Option<Read<'a, resources::GameClock>>,
+        ReadStorage<'a, components::Position>,
+        ReadStorage<'a, components::Sprite>,
+        ReadStorage<'a, components::Visible>,
+        ReadStorage<'a, components::Explored>,
+        ReadStorage<'a, components::Shape>,
+        ReadStorage<'a, components::Health>,
+        ReadStorage<'a, components::Attack>,
+        ReadStorage<'a, components::Defense>,
+        ReadStorage<'a, components::Inventory>,
+        ReadStorage<'a, components::Name>,
+        ReadStorage<'a, equipment::Equipment>,
+        ReadStorage<'a, equipment::Equippable>,
+        ReadStorage<'a, components::material::Material>,
+        Option<Read<'a, GameLog>>,
+        Option<Read<'a, resources::InventoryScreen>>,
+        Write<'a, resources::ViewportSize>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            camera_center,
+            player,
+            light_map,
+            clock,
+            pos,
+            draw,
+            visible,
+            explored,
+            shapes,
+            healths,
+            attacks,
+            defenses,
+            inventories,
+            names,
+            equipment,
+            equippables,
+            materials,
+            game_log,
+            inventory_screen,
+            mut viewport,
+        ): Self::SystemData,
+    ) {
+        use specs::Join;
+
+        let (screen_width, screen_height) = self.renderer.size();
+
+        // Only worth showing the player's stats once they have some
+        let player = player.map(|player| player.ent);
+        let name_of = |item: specs::Entity| {
+            names
+                .get(item)
+                .map_or_else(|| String::from("item"), |name| name.name.clone())
+        };
+
+        let status = player.and_then(|player| {
+            let bonuses = equipment::bonuses_of(player, &equipment, &equippables, &materials);
+            let slots = equipment.get(player).map_or_else(Vec::new, |equipment| {
+                equipment::Slot::ALL
+                    .iter()
+                    .map(|slot| (slot.label(), equipment.get(*slot).map(name_of)))
+                    .collect()
+            });
+
+            healths.get(player).map(|health| hud::Status {
+                health: Some((health.current, health.max)),
+                attack: attacks
+                    .get(player)
+                    .map(|attack| attack.power + bonuses.attack),
+                defense: defenses
+                    .get(player)
+                    .map(|defense| defense.armor + bonuses.defense),
+                time: clock.as_ref().map_or(0, |clock| clock.time),
+                equipment: slots,
+            })
+        });
+
+        let layout = Layout::new(
+            screen_width,
+            screen_height,
+            game_log.is_some(),
+            status.is_some(),
+        );
+
+        let width = layout.map.width;
+        let height = layout.map.height;
+
+        viewport.width = width;
+        viewport.height = height;
+
+        let half_width = width as i32 / 2;
+        let half_height = height as i32 / 2;
+
+        let min_x = camera_center.x - half_width;
+        let max_x = camera_center.x + half_width + (width as i32 % 2) - 1;
+        let min_y = camera_center.y - half_height;
+        let max_y = camera_center.y + half_height + (height as i32 % 2) - 1;
+
+        let offset_x = if min_x < 0 { min_x - 1 } else { min_x };
+        let offset_y = if min_y < 0 { min_y - 1 } else { min_y };
+
+        let mut to_draw: Vec<(usize, usize, components::Sprite)> = vec![];
+
+        let blank_rune = '?';
+
+        let blank = components::Sprite {
+            fg_r: 60,
+            fg_g: 0,
+            fg_b: 0,
+
+            bg_r: 0,
+            bg_g: 0,
+            bg_b: 0,
+
+            layer: components::DL_FLOOR,
+
+            rune: blank_rune,
+        };
+
+        let explored = player.and_then(|player| explored.get(player));
+
+        for (pos, draw, visible, shape) in
+            (&pos, &draw, (&visible).maybe(), (&shapes).maybe()).join()
+        {
+            let tile_x = pos.x as i32;
+            let tile_y = pos.y as i32;
+
+            if tile_x < min_x || tile_x >= max_x || tile_y < min_y || tile_y >= max_y {
+                continue;
+            }
+
+            // Out of sight creatures could have gone anywhere, so only the
+            // terrain gets remembered
+            let light = light_map.as_ref().and_then(|lights| lights.light_at(pos));
+
+            let draw = if visible.is_some() {
+                match light {
+                    Some(light) => tinted(draw, light),
+                    None => draw.clone(),
+                }
+            } else if is_terrain(shape) && explored.is_some_and(|e| e.tiles.contains(pos)) {
+                remembered(draw)
+            } else {
+                continue;
+            };
+
+            to_draw.push((
+                (tile_x - offset_x) as usize,
+                (tile_y - offset_y) as usize,
+                draw,
+            ));
+        }
+
+        to_draw.sort_by(|a, b| a.2.layer.cmp(&b.2.layer));
+
+        let mut frame = Frame::new(screen_width, screen_height, blank.clone());
+        let mut map = frame.canvas(layout.map);
+
+        for (tile_x, tile_y, draw) in to_draw {
+            let under = match map.get(tile_x, tile_y) {
+                Some(under) => under,
+                None => continue,
+            };
+
+            if under.rune == blank_rune {
+                map.put(tile_x, tile_y, draw);
+            } else {
+                let blended = components::Sprite {
+                    fg_r: draw.fg_r,
+                    fg_g: draw.fg_g,
+                    fg_b: draw.fg_b,
+
+                    bg_r: under.bg_r / 2 + draw.bg_r / 2,
+                    bg_g: under.bg_g / 2 + draw.bg_g / 2,
+                    bg_b: under.bg_b / 2 + draw.bg_b / 2,
+
+                    layer: draw.layer.clone(),
+
+                    rune: draw.rune,
+                };
+
+                map.put(tile_x, tile_y, blended);
+            }
+        }
+
+        if let (Some(rect), Some(status)) = (layout.sidebar, &status) {
+            hud::draw_sidebar(&mut frame.canvas(rect), status);
+        }
+
+        if let (Some(rect), Some(log)) = (layout.log, &game_log) {
+            hud::draw_log(&mut frame.canvas(rect), log);
+        }
+
+        let open_inventory = player
+            .filter(|_| inventory_screen.as_ref().is_some_and(|screen| screen.open))
+            .and_then(|player| inventories.get(player));
+
+        if let (Some(inventory), Some(screen)) = (open_inventory, &inventory_screen) {
+            let worn = player.and_then(|player| equipment.get(player));
+            let lines: Vec<widgets::Line> = inventory
+                .items
+                .iter()
+                .map(|item| {
+                    let mut name = name_of(*item);
+
+                    if worn.is_some_and(|worn| worn.slot_of(*item).is_some()) {
+                        name.push_str(" (equipped)");
+                    }
+                    let color = draw.get(*item).map_or((230, 230, 230), |sprite| {
+                        (sprite.fg_r, sprite.fg_g, sprite.fg_b)
+                    });
+
+                    widgets::Line::new(name, color)
+                })
+                .collect();
+
+            let rect = layout.modal(INVENTORY_WIDTH, INVENTORY_HEIGHT);
+
+            hud::draw_inventory(&mut frame.canvas(rect), &lines, screen.selected);
+        }
+
+        if screen_width != self.back_buffer_width || screen_height != self.back_buffer_height {
+            self.back_buffer = vec![blank.clone(); screen_width * screen_height];
+            self.back_buffer_width = screen_width;
+            self.back_buffer_height = screen_height;
+        }
+
+        for x in 0..screen_width {
+            for y in 0..screen_height {
+                let i = y * screen_width + x;
+                let draw = &frame.cells[i];
+
+                if *draw != self.back_buffer[i] {
+                    self.back_buffer[i] = draw.clone();
+                    self.renderer.draw_at(
+                        x + 1,
+                        y + 1,
+                        &Color {
+                            r: draw.fg_r,
+                            g: draw.fg_g,
+                            b: draw.fg_b,
+                        },
+                        &Color {
+                            r: draw.bg_r,
+                            g: draw.bg_g,
+                            b: draw.bg_b,
+                        },
+                        draw.rune,
+                    );
+                }
+            }
+        }
+
+        self.renderer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    struct MockRenderer {
+        width: usize,
+        height: usize,
+
+        drew_at_x: Option<usize>,
+        drew_at_y: Option<usize>,
+        drew_fg: Option<Color>,
+        drew_bg: Option<Color>,
+        drew_rune: Option<char>,
+        drew_count: usize,
+        flush_count: usize,
+
+        // Every rune drawn so far, by where it was drawn
+        screen: std::collections::HashMap<(usize, usize), char>,
+    }
+
+    impl MockRenderer {
+        fn new(width: usize, height: usize) -> MockRenderer {
+            MockRenderer {
+                width,
+                height,
+
+                drew_at_x: None,
+                drew_at_y: None,
+
+                drew_fg: None,
+                drew_bg: None,
+                drew_rune: None,
+
+                drew_count: 0,
+                flush_count: 0,
+
+                screen: std::collections::HashMap::new(),
+            }
+        }
+    }
+
+    impl Renderer for MockRenderer {
+        fn draw_at(&mut self, x: usize, y: usize, fg: &Color, bg: &Color, rune: char) {
+            self.drew_at_x = Some(x);
+            self.drew_at_y = Some(y);
+            self.drew_fg = Some(fg.clone());
+            self.drew_bg = Some(bg.clone());
+            self.drew_rune = Some(rune);
+            self.drew_count += 1;
+            self.screen.insert((x, y), rune);
+        }
+
+        fn size(&self) -> (usize, usize) {
+            (self.width, self.height)
+        }
+
+        fn flush(&mut self) {
+            self.flush_count += 1;
+        }
+    }
+
+    fn build_world(camera_x: i32, camera_y: i32) -> World {
+        let mut world = World::new();
+
+        world.register::<components::Position>();
+        world.register::<components::Sprite>();
+        world.register::<components::Visible>();
+        world.register::<components::Explored>();
+        world.register::<components::Shape>();
+        world.register::<components::Health>();
+        world.register::<components::Attack>();
+        world.register::<components::Defense>();
+        world.register::<components::Inventory>();
+        world.register::<components::Name>();
+        world.register::<equipment::Equipment>();
+        world.register::<equipment::Equippable>();
+        world.register::<components::material::Material>();
+
+        world.insert(resources::CameraCenter {
+            x: camera_x,
+            y: camera_y,
+        });
+        world.insert(resources::ViewportSize::default());
+
+        world
+    }
+
+    #[test]
+    fn culls_entities_outside_camera_but_draws_entities_inside() {
+        let fg_r = 37;
+        let fg_g = 84;
+        let fg_b = 244;
+        let bg_r = 43;
+        let bg_g = 134;
+        let bg_b = 101;
+        let visible_x = 1;
+        let visible_y = -51;
+        let rune = '+';
+        let camera_x = 2;
+        let camera_y = -50;
+        let width = 5;
+        let height = 5;
+        let expected_x = 2;
+        let expected_y = 3;
+        let mock_renderer = MockRenderer::new(width, height);
+
+        let mut world = build_world(camera_x, camera_y);
+
+        let mut spawn = |x: i32, y: i32, visible: bool| {
+            let mut builder = world
+                .create_entity()
+                .with(components::Position { x, y })
+                .with(components::Sprite {
+                    fg_r,
+                    fg_g,
+                    fg_b,
+
+                    bg_r,
+                    bg_g,
+                    bg_b,
+
+                    layer: components::DL_ENTITY,
+
+                    rune,
+                });
+
+            if visible {
+                builder = builder.with(components::Visible);
+            }
+
+            builder.build();
+        };
+
+        // This should be visible because it's on screen and has the Visible component
+        spawn(visible_x, visible_y, true);
+
+        // This should be invisible due to lacking Visible component
+        spawn(visible_x, visible_y, false);
+
+        // Off to the right
+        spawn(5, visible_y, true);
+
+        // Off to the left
+        spawn(-2, visible_y, true);
+
+        // Above
+        spawn(visible_x, -54, true);
+
+        // Below
+        spawn(visible_x, -46, true);
+
+        let mut render = Render::new(mock_renderer);
+
+        render.run_now(&world);
+        world.maintain();
+
+        // Should only have one thing drawn, the others should be culled
+        assert_eq!(render.renderer.drew_count, 1);
+
+        assert_eq!(render.renderer.flush_count, 1);
+
+        assert_eq!(render.renderer.drew_at_x.unwrap(), expected_x);
+        assert_eq!(render.renderer.drew_at_y.unwrap(), expected_y);
+
+        assert_eq!(render.renderer.drew_rune.unwrap(), rune);
+
+        assert_eq!(
+            render.renderer.drew_fg.unwrap(),
+            Color {
+                r: fg_r,
+                g: fg_g,
+                b: fg_b
+            }
+        );
+
+        assert_eq!(
+            render.renderer.drew_bg.unwrap(),
+            Color {
+                r: bg_r,
+                g: bg_g,
+                b: bg_b
+            }
+        );
+    }
+
+    #[test]
+    fn draws_entities_over_map() {
+        let lower_rune = '_';
+        let upper_rune = '+';
+        let camera_x = 2;
+        let camera_y = -50;
+        let width = 5;
+        let height = 5;
+        let mock_renderer = MockRenderer::new(width, height);
+
+        let mut world = build_world(camera_x, camera_y);
+
+        let mut spawn = |x: i32, y: i32, rune: char, layer: components::DrawLayer| {
+            world
+                .create_entity()
+                .with(components::Position { x, y })
+                .with(components::Sprite {
+                    fg_r: 255,
+                    fg_g: 255,
+                    fg_b: 255,
+
+                    bg_r: 255,
+                    bg_g: 255,
+                    bg_b: 255,
+
+                    layer,
+
+                    rune,
+                })
+                .with(components::Visible)
+                .build()
+        };
+
+        // Add a bunch extra to hedge against any potential hidden randomization
+        for _ in 0..500 {
+            spawn(camera_x, camera_y, lower_rune, components::DL_FLOOR);
+        }
+        spawn(camera_x, camera_y, upper_rune, components::DL_ENTITY);
+        for _ in 0..500 {
+            spawn(camera_x, camera_y, lower_rune, components::DL_FLOOR);
+        }
+
+        let mut render = Render::new(mock_renderer);
+
+        render.run_now(&world);
+        world.maintain();
+
+        assert_eq!(render.renderer.drew_rune.unwrap(), upper_rune);
+        assert_eq!(render.renderer.drew_count, 1);
+    }
+
+    #[test]
+    fn draws_remembered_terrain_dimmed_and_hides_unseen_creatures() {
+        let width = 5;
+        let height = 5;
+        let remembered_pos = components::Position::new(2, 2);
+        let floor = components::Sprite {
+            fg_r: 200,
+            fg_g: 200,
+            fg_b: 200,
+
+            bg_r: 200,
+            bg_g: 100,
+            bg_b: 0,
+
+            layer: components::DL_FLOOR,
+
+            rune: '.',
+        };
+
+        let mut world = build_world(2, 2);
+
+        let mut explored = components::Explored::default();
+
+        explored.tiles.insert(remembered_pos.clone());
+
+        let ent_player = world.create_entity().with(explored).build();
+
+        world.insert(resources::Player { ent: ent_player });
+
+        world
+            .create_entity()
+            .with(remembered_pos.clone())
+            .with(floor.clone())
+            .with(components::Shape::Floor)
+            .build();
+
+        // Never seen, so it shouldn't show up at all
+        world
+            .create_entity()
+            .with(remembered_pos.right())
+            .with(floor.clone())
+            .with(components::Shape::Floor)
+            .build();
+
+        world
+            .create_entity()
+            .with(remembered_pos.clone())
+            .with(components::Sprite {
+                layer: components::DL_ENTITY,
+                rune: 'g',
+                ..floor.clone()
+            })
+            .with(components::Shape::Medium)
+            .build();
+
+        let mut render = Render::new(MockRenderer::new(width, height));
+
+        render.run_now(&world);
+        world.maintain();
+
+        assert_eq!(render.renderer.drew_count, 1);
+        assert_eq!(render.renderer.drew_rune.unwrap(), '.');
+
+        let bg = render.renderer.drew_bg.unwrap();
+
+        assert!(bg.r < floor.bg_r && bg.g < floor.bg_g);
+        assert!(bg.r - bg.b < floor.bg_r - floor.bg_b);
+    }
+
+    #[test]
+    fn tints_visible_sprites_by_their_light() {
+        let pos = components::Position::new(2, 2);
+
+        let mut world = build_world(2, 2);
+
+        let mut light_map = resources::LightMap::default();
+
+        light_map.tiles.insert(
+            pos.clone(),
+            resources::Light {
+                r: 1.,
+                g: 0.,
+                b: 0.5,
+            },
+        );
+
+        world.insert(light_map);
+
+        world
+            .create_entity()
+            .with(pos)
+            .with(components::Sprite {
+                fg_r: 200,
+                fg_g: 200,
+                fg_b: 200,
+
+                bg_r: 100,
+                bg_g: 100,
+                bg_b: 100,
+
+                layer: components::DL_ENTITY,
+
+                rune: '@',
+            })
+            .with(components::Visible)
+            .build();
+
+        let mut render = Render::new(MockRenderer::new(5, 5));
+
+        render.run_now(&world);
+        world.maintain();
+
+        let fg = render.renderer.drew_fg.unwrap();
+
+        assert_eq!(fg.r, 200);
+        assert!(fg.g < fg.b);
+        assert!(fg.b < fg.r);
+    }
+
+    #[test]
+    fn draws_latest_log_messages_under_the_map() {
+        let width = 20;
+        let height = 10;
+        let mut log = GameLog::default();
+
+        let mut world = build_world(0, 0);
+
+        for i in 0..layout::LOG_ROWS + 2 {
+            log.push(format!("Message {}", i), (255, 255, 255));
+        }
+
+        world.insert(log);
+
+        let mut render = Render::new(MockRenderer::new(width, height));
+
+        render.run_now(&world);
+        world.maintain();
+
+        let row = |y: usize| -> String {
+            (1..=width)
+                .map(|x| render.renderer.screen.get(&(x, y)).cloned().unwrap_or(' '))
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+
+        let map_height = height - layout::LOG_ROWS - 1;
+
+        assert_eq!(
+            world.read_resource::<resources::ViewportSize>().height,
+            map_height
+        );
+        assert_eq!(row(map_height + 1), "─".repeat(width));
+        assert_eq!(row(map_height + 2), "Message 2");
+        assert_eq!(row(height), format!("Message {}", layout::LOG_ROWS + 1));
+    }
+
+    #[test]
+    fn shows_player_stats_beside_the_map() {
+        let width = 50;
+        let height = 10;
+
+        let mut world = build_world(0, 0);
+
+        let ent_player = world
+            .create_entity()
+            .with(components::Health::new(30))
+            .build();
+
+        world.insert(resources::Player { ent: ent_player });
+
+        let mut render = Render::new(MockRenderer::new(width, height));
+
+        render.run_now(&world);
+        world.maintain();
+
+        let sidebar_x = width - layout::SIDEBAR_WIDTH + 1;
+
+        assert_eq!(
+            world.read_resource::<resources::ViewportSize>().width,
+            width - layout::SIDEBAR_WIDTH
+        );
+        assert_eq!(render.renderer.screen.get(&(sidebar_x, 1)), Some(&'│'));
+        assert_eq!(render.renderer.screen.get(&(sidebar_x + 2, 2)), Some(&'H'));
+        assert_eq!(render.renderer.screen.get(&(sidebar_x + 2, 3)), Some(&'█'));
+    }
+}
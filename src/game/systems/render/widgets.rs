@@ -0,0 +1,309 @@
+use super::canvas::{text_sprite, Canvas};
+use super::layout::Rect;
+
+/// A line of text in a list, in its own color
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub text: String,
+    pub color: (u8, u8, u8),
+}
+
+impl Line {
+    pub fn new(text: impl Into<String>, color: (u8, u8, u8)) -> Line {
+        Line {
+            text: text.into(),
+            color,
+        }
+    }
+}
+
+/// Draws a border around the edge of the canvas with an optional title in the
+/// top edge, and returns the area left inside it
+pub fn draw_box(canvas: &mut Canvas, title: Option<&str>, color: (u8, u8, u8)) -> Rect {
+    let width = canvas.width();
+    let height = canvas.height();
+
+    if width < 2 || height < 2 {
+        return Rect::default();
+    }
+
+    canvas.fill(&text_sprite(' ', color));
+
+    for x in 1..width - 1 {
+        canvas.put(x, 0, text_sprite('─', color));
+        canvas.put(x, height - 1, text_sprite('─', color));
+    }
+
+    for y in 1..height - 1 {
+        canvas.put(0, y, text_sprite('│', color));
+        canvas.put(width - 1, y, text_sprite('│', color));
+    }
+
+    canvas.put(0, 0, text_sprite('┌', color));
+    canvas.put(width - 1, 0, text_sprite('┐', color));
+    canvas.put(0, height - 1, text_sprite('└', color));
+    canvas.put(width - 1, height - 1, text_sprite('┘', color));
+
+    if let Some(title) = title {
+        let title = format!(" {} ", title);
+
+        // Leave the corners alone however long the title is
+        canvas
+            .area(Rect::new(1, 0, width - 2, 1))
+            .draw_text(1, 0, &title, color);
+    }
+
+    Rect::new(1, 1, width - 2, height - 2)
+}
+
+/// A horizontal bar filled in proportion to how much of the maximum the value
+/// is, such as health
+pub fn draw_progress_bar(
+    canvas: &mut Canvas,
+    x: usize,
+    y: usize,
+    width: usize,
+    (value, max): (i32, i32),
+    filled: (u8, u8, u8),
+    empty: (u8, u8, u8),
+) {
+    let fraction = if max > 0 {
+        value.clamp(0, max) as f32 / max as f32
+    } else {
+        0.
+    };
+
+    // Anything left at all shows as at least a sliver
+    let mut cells = (fraction * width as f32).round() as usize;
+
+    if value > 0 && cells == 0 {
+        cells = 1;
+    }
+
+    for i in 0..width {
+        let color = if i < cells { filled } else { empty };
+
+        canvas.put(x + i, y, text_sprite('█', color));
+    }
+}
+
+/// Which entry of a list is picked, and how far down the list has been
+/// scrolled to show it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListState {
+    pub selected: usize,
+    pub offset: usize,
+}
+
+impl ListState {
+    pub fn select_next(&mut self, len: usize) {
+        if len > 0 {
+            self.selected = (self.selected + 1).min(len - 1);
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Scrolls just far enough for the selected entry to be among the rows
+    pub fn scroll_into_view(&mut self, rows: usize) {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if rows > 0 && self.selected >= self.offset + rows {
+            self.offset = self.selected + 1 - rows;
+        }
+    }
+}
+
+/// As many lines as fit, starting from the offset.  Arrows in the last column
+/// show when there's more above or below.
+pub fn draw_list(canvas: &mut Canvas, lines: &[Line], offset: usize) {
+    let rows = canvas.height();
+
+    for (row, line) in lines.iter().skip(offset).take(rows).enumerate() {
+        canvas.draw_text(0, row, &line.text, line.color);
+    }
+
+    draw_scroll_arrows(canvas, lines.len(), offset);
+}
+
+fn draw_scroll_arrows(canvas: &mut Canvas, len: usize, offset: usize) {
+    let rows = canvas.height();
+    let width = canvas.width();
+
+    if width > 0 && rows > 0 {
+        if offset > 0 {
+            canvas.put(width - 1, 0, text_sprite('↑', (160, 160, 160)));
+        }
+
+        if offset + rows < len {
+            canvas.put(width - 1, rows - 1, text_sprite('↓', (160, 160, 160)));
+        }
+    }
+}
+
+/// A list with one entry picked out, scrolled so the pick is always in view
+pub fn draw_menu(canvas: &mut Canvas, lines: &[Line], state: &mut ListState) {
+    if lines.is_empty() {
+        return;
+    }
+
+    state.selected = state.selected.min(lines.len() - 1);
+    state.scroll_into_view(canvas.height());
+
+    draw_list(canvas, lines, state.offset);
+
+    let row = state.selected - state.offset;
+    let line = &lines[state.selected];
+    let width = canvas.width();
+
+    // The picked entry is shown inverted across the whole row
+    let text = format!("{:width$}", line.text, width = width);
+
+    canvas.draw_text_on(0, row, &text, (0, 0, 0), line.color);
+
+    // Put back any arrow the pick was drawn over
+    draw_scroll_arrows(canvas, lines.len(), state.offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::canvas::Frame;
+    use super::*;
+
+    const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+    fn frame(width: usize, height: usize) -> Frame {
+        Frame::new(width, height, text_sprite(' ', (0, 0, 0)))
+    }
+
+    fn runes(frame: &Frame) -> Vec<String> {
+        (0..frame.height)
+            .map(|y| {
+                (0..frame.width)
+                    .map(|x| frame.get(x, y).unwrap().rune)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn lines(count: usize) -> Vec<Line> {
+        (0..count)
+            .map(|i| Line::new(format!("item {}", i), WHITE))
+            .collect()
+    }
+
+    #[test]
+    fn boxes_have_borders_and_titles() {
+        let mut frame = frame(8, 3);
+        let inner = draw_box(&mut frame.canvas(Rect::new(0, 0, 8, 3)), Some("Hi"), WHITE);
+
+        assert_eq!(runes(&frame), vec!["┌─ Hi ─┐", "│      │", "└──────┘"]);
+        assert_eq!(inner, Rect::new(1, 1, 6, 1));
+    }
+
+    #[test]
+    fn long_titles_leave_the_corners_alone() {
+        let mut frame = frame(6, 2);
+
+        draw_box(
+            &mut frame.canvas(Rect::new(0, 0, 6, 2)),
+            Some("Inventory"),
+            WHITE,
+        );
+
+        assert_eq!(runes(&frame)[0], "┌─ In┐");
+    }
+
+    #[test]
+    fn progress_bars_fill_in_proportion() {
+        let mut frame = frame(10, 1);
+        let filled = (0, 255, 0);
+
+        draw_progress_bar(
+            &mut frame.canvas(Rect::new(0, 0, 10, 1)),
+            0,
+            0,
+            10,
+            (3, 10),
+            filled,
+            (40, 40, 40),
+        );
+
+        let filled_cells = (0..10)
+            .filter(|x| frame.get(*x, 0).unwrap().fg_g == filled.1)
+            .count();
+
+        assert_eq!(filled_cells, 3);
+    }
+
+    #[test]
+    fn nearly_empty_bars_still_show_something() {
+        let mut frame = frame(10, 1);
+
+        draw_progress_bar(
+            &mut frame.canvas(Rect::new(0, 0, 10, 1)),
+            0,
+            0,
+            10,
+            (1, 100),
+            (0, 255, 0),
+            (40, 40, 40),
+        );
+
+        assert_eq!(frame.get(0, 0).unwrap().fg_g, 255);
+        assert_eq!(frame.get(1, 0).unwrap().fg_g, 40);
+    }
+
+    #[test]
+    fn lists_show_when_there_is_more() {
+        let mut frame = frame(8, 2);
+
+        draw_list(&mut frame.canvas(Rect::new(0, 0, 8, 2)), &lines(4), 1);
+
+        assert_eq!(runes(&frame), vec!["item 1 ↑", "item 2 ↓"]);
+    }
+
+    #[test]
+    fn menus_scroll_to_keep_the_pick_in_view() {
+        let mut frame = frame(8, 2);
+        let lines = lines(5);
+        let mut state = ListState::default();
+
+        for _ in 0..3 {
+            state.select_next(lines.len());
+        }
+
+        draw_menu(&mut frame.canvas(Rect::new(0, 0, 8, 2)), &lines, &mut state);
+
+        assert_eq!(state.offset, 2);
+        assert_eq!(runes(&frame), vec!["item 2 ↑", "item 3 ↓"]);
+
+        // The pick is drawn inverted
+        let picked = frame.get(0, 1).unwrap();
+
+        assert_eq!((picked.fg_r, picked.bg_r), (0, 255));
+
+        // Arrows stay in view over the pick at either end
+        state.select_previous();
+        draw_menu(&mut frame.canvas(Rect::new(0, 0, 8, 2)), &lines, &mut state);
+
+        assert_eq!(state.offset, 2);
+        assert_eq!(runes(&frame), vec!["item 2 ↑", "item 3 ↓"]);
+    }
+
+    #[test]
+    fn selection_stops_at_the_ends() {
+        let mut state = ListState::default();
+
+        state.select_previous();
+        assert_eq!(state.selected, 0);
+
+        for _ in 0..10 {
+            state.select_next(3);
+        }
+
+        assert_eq!(state.selected, 2);
+    }
+}
@@ -0,0 +1,171 @@
+use super::super::{components, resources};
+use specs::{ReadStorage, System, Write, WriteStorage};
+
+/// Decides who acts this dispatch.  Time only moves forward once nobody is
+/// ready, so the world waits on the player whenever it's their turn and
+/// otherwise runs until someone can act again.
+pub struct Scheduler;
+
+fn find_ready(
+    entities: &specs::Entities,
+    energies: &WriteStorage<components::Energy>,
+    players: &ReadStorage<components::Player>,
+) -> (bool, Vec<specs::Entity>) {
+    use specs::Join;
+
+    let mut player_ready = false;
+    let mut ready = Vec::new();
+
+    for (entity, energy, player) in (entities, energies, players.maybe()).join() {
+        if !energy.is_ready() {
+            continue;
+        }
+
+        if player.is_some() {
+            player_ready = true;
+        } else {
+            ready.push((energy.value, entity));
+        }
+    }
+
+    // Ties go to whoever was created first so the order is always the same
+    ready.sort_by_key(|(value, entity)| (-value, entity.id()));
+
+    (
+        player_ready,
+        ready.into_iter().map(|(_, entity)| entity).collect(),
+    )
+}
+
+impl<'a> System<'a> for Scheduler {
+    type SystemData = (
+        specs::Entities<'a>,
+        WriteStorage<'a, components::Energy>,
+        ReadStorage<'a, components::Player>,
+        Write<'a, resources::GameClock>,
+        Write<'a, resources::Turn>,
+    );
+
+    fn run(&mut self, (entities, mut energies, players, mut clock, mut turn): Self::SystemData) {
+        use specs::Join;
+
+        clock.ticked = false;
+
+        let (mut player_ready, mut ready) = find_ready(&entities, &energies, &players);
+
+        if !player_ready && ready.is_empty() {
+            for energy in (&mut energies).join() {
+                energy.value += energy.speed;
+            }
+
+            clock.time += 1;
+            clock.ticked = true;
+
+            let found = find_ready(&entities, &energies, &players);
+
+            player_ready = found.0;
+            ready = found.1;
+        }
+
+        turn.player_ready = player_ready;
+        turn.ready = ready;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn build_world() -> World {
+        let mut world = World::new();
+
+        world.register::<components::Energy>();
+        world.register::<components::Player>();
+        world.insert(resources::GameClock::default());
+        world.insert(resources::Turn::default());
+
+        world
+    }
+
+    fn energy(value: i32, speed: i32) -> components::Energy {
+        components::Energy { value, speed }
+    }
+
+    #[test]
+    fn waits_for_ready_player() {
+        let mut world = build_world();
+
+        world
+            .create_entity()
+            .with(energy(components::ACTION_COST, 10))
+            .with(components::Player)
+            .build();
+
+        Scheduler.run_now(&world);
+        world.maintain();
+
+        let clock = world.read_resource::<resources::GameClock>();
+
+        assert!(world.read_resource::<resources::Turn>().player_ready);
+        assert_eq!(clock.time, 0);
+        assert!(!clock.ticked);
+    }
+
+    #[test]
+    fn time_passes_when_nobody_is_ready() {
+        let mut world = build_world();
+
+        let ent = world.create_entity().with(energy(0, 10)).build();
+
+        Scheduler.run_now(&world);
+        world.maintain();
+
+        assert_eq!(world.read_resource::<resources::GameClock>().time, 1);
+        assert!(world.read_resource::<resources::GameClock>().ticked);
+        assert_eq!(
+            world
+                .read_storage::<components::Energy>()
+                .get(ent)
+                .unwrap()
+                .value,
+            10
+        );
+    }
+
+    #[test]
+    fn faster_actors_get_ready_sooner() {
+        let mut world = build_world();
+
+        let ent_slow = world.create_entity().with(energy(0, 10)).build();
+        let ent_fast = world.create_entity().with(energy(0, 25)).build();
+
+        for _ in 0..4 {
+            Scheduler.run_now(&world);
+            world.maintain();
+        }
+
+        let turn = world.read_resource::<resources::Turn>();
+
+        assert_eq!(turn.ready, vec![ent_fast]);
+        assert!(!turn.ready.contains(&ent_slow));
+    }
+
+    #[test]
+    fn most_energetic_acts_first() {
+        let mut world = build_world();
+
+        let ent_a = world.create_entity().with(energy(100, 10)).build();
+        let ent_b = world.create_entity().with(energy(150, 10)).build();
+        let ent_c = world.create_entity().with(energy(100, 10)).build();
+
+        Scheduler.run_now(&world);
+        world.maintain();
+
+        assert_eq!(
+            world.read_resource::<resources::Turn>().ready,
+            vec![ent_b, ent_a, ent_c]
+        );
+        assert_eq!(world.read_resource::<resources::GameClock>().time, 0);
+    }
+}
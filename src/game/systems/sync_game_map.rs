@@ -2,9 +2,72 @@ use super::super::{
     components,
     resources::game_map::{GameMap, TileProperties},
 };
-use specs::{ReadStorage, System, Write};
+use specs::{
+    hibitset::BitSet, shrev::ReaderId, storage::ComponentEvent, ReadStorage, System, SystemData,
+    World, Write, WriteStorage,
+};
+
+/// Keeps the game map up to date by listening for changes to positions,
+/// materials, shapes and items instead of rebuilding everything every tick
+#[derive(Default)]
+pub struct SyncGameMap {
+    position_reader: Option<ReaderId<ComponentEvent>>,
+    material_reader: Option<ReaderId<ComponentEvent>>,
+    shape_reader: Option<ReaderId<ComponentEvent>>,
+    item_reader: Option<ReaderId<ComponentEvent>>,
+}
+
+/// How much the entity blocks sight through its tile
+fn opacity(
+    material: Option<&components::material::Material>,
+    shape: Option<&components::Shape>,
+) -> f32 {
+    match (material, shape) {
+        (Some(material), Some(shape)) => material.opacity * shape.obstruction(),
+        _ => 0.,
+    }
+}
 
-pub struct SyncGameMap;
+fn tile_properties(
+    material: Option<&components::material::Material>,
+    shape: Option<&components::Shape>,
+    is_item: bool,
+) -> TileProperties {
+    let mut properties = TileProperties::empty();
+
+    if let (Some(material), Some(shape)) = (material, shape) {
+        properties.set(
+            TileProperties::BLOCKED,
+            material.solid && *shape == components::Shape::FullBlock,
+        );
+        properties.set(
+            TileProperties::OCCUPIED,
+            material.solid && shape.occupies_tile(),
+        );
+    }
+
+    properties.set(TileProperties::OPAQUE, opacity(material, shape) >= 1.);
+    properties.set(TileProperties::ITEMS, is_item);
+
+    properties
+}
+
+fn read_events(
+    events: specs::shrev::EventIterator<ComponentEvent>,
+    dirty: &mut BitSet,
+    removed: &mut BitSet,
+) {
+    for event in events {
+        match event {
+            ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                dirty.add(*id);
+            }
+            ComponentEvent::Removed(id) => {
+                removed.add(*id);
+            }
+        }
+    }
+}
 
 impl<'a> System<'a> for SyncGameMap {
     type SystemData = (
@@ -13,26 +76,86 @@ impl<'a> System<'a> for SyncGameMap {
         ReadStorage<'a, components::Position>,
         ReadStorage<'a, components::material::Material>,
         ReadStorage<'a, components::Shape>,
+        ReadStorage<'a, components::Item>,
     );
 
-    fn run(&mut self, (entities, mut game_map, positions, materials, shapes): Self::SystemData) {
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+
+        self.position_reader =
+            Some(WriteStorage::<components::Position>::fetch(world).register_reader());
+        self.material_reader =
+            Some(WriteStorage::<components::material::Material>::fetch(world).register_reader());
+        self.shape_reader = Some(WriteStorage::<components::Shape>::fetch(world).register_reader());
+        self.item_reader = Some(WriteStorage::<components::Item>::fetch(world).register_reader());
+    }
+
+    fn run(
+        &mut self,
+        (entities, mut game_map, positions, materials, shapes, items): Self::SystemData,
+    ) {
         use specs::Join;
 
-        // This is super inefficient and we should figure out how to do this only
-        // for actual changes we care about, but for now simplicity wins
-        game_map.clear_all();
+        let mut dirty = BitSet::new();
+        let mut removed_positions = BitSet::new();
 
-        for (entity, pos, material, shape) in (&entities, &positions, &materials, &shapes).join() {
-            game_map.add(&pos, entity.clone());
+        // Losing a material, shape or being an item just changes what the
+        // entity does to its tile, only losing the position takes it off the map
+        let mut changed = BitSet::new();
 
-            if material.solid {
-                match shape {
-                    components::Shape::FullBlock => {
-                        game_map.mark_tile(&pos, TileProperties::BLOCKED)
-                    }
-                    _ => (),
-                };
-            }
+        read_events(
+            positions.channel().read(
+                self.position_reader
+                    .as_mut()
+                    .expect("SyncGameMap was not set up"),
+            ),
+            &mut dirty,
+            &mut removed_positions,
+        );
+        read_events(
+            materials.channel().read(
+                self.material_reader
+                    .as_mut()
+                    .expect("SyncGameMap was not set up"),
+            ),
+            &mut dirty,
+            &mut changed,
+        );
+        read_events(
+            shapes.channel().read(
+                self.shape_reader
+                    .as_mut()
+                    .expect("SyncGameMap was not set up"),
+            ),
+            &mut dirty,
+            &mut changed,
+        );
+        read_events(
+            items.channel().read(
+                self.item_reader
+                    .as_mut()
+                    .expect("SyncGameMap was not set up"),
+            ),
+            &mut dirty,
+            &mut changed,
+        );
+
+        for id in (&removed_positions).join() {
+            game_map.remove_entity(id);
+        }
+
+        dirty |= &changed;
+
+        for (entity, pos, _) in (&entities, &positions, &dirty).join() {
+            let material = materials.get(entity);
+            let shape = shapes.get(entity);
+
+            game_map.place_with_opacity(
+                pos,
+                entity,
+                tile_properties(material, shape, items.contains(entity)),
+                opacity(material, shape),
+            );
         }
     }
 }
@@ -42,7 +165,7 @@ mod tests {
     use super::*;
     use specs::{Builder, RunNow, World, WorldExt};
 
-    fn build_world() -> specs::World {
+    fn build_world() -> (specs::World, SyncGameMap) {
         let mut world = World::new();
         let game_map = GameMap::new();
 
@@ -52,13 +175,22 @@ mod tests {
         world.register::<components::Moved>();
         world.register::<components::material::Material>();
         world.register::<components::Shape>();
+        world.register::<components::Item>();
 
-        world
+        let mut sync_game_map = SyncGameMap::default();
+        System::setup(&mut sync_game_map, &mut world);
+
+        (world, sync_game_map)
+    }
+
+    fn sync(world: &mut specs::World, sync_game_map: &mut SyncGameMap) {
+        sync_game_map.run_now(world);
+        world.maintain();
     }
 
     #[test]
     fn entity_without_solid_doesnt_block_tile() {
-        let mut world = build_world();
+        let (mut world, mut sync_game_map) = build_world();
         let pos = components::Position::new(3, -4);
 
         world
@@ -68,17 +200,161 @@ mod tests {
             .with(components::Shape::FullBlock)
             .build();
 
-        let mut sync_game_map = SyncGameMap;
-        sync_game_map.run_now(&world);
-        world.maintain();
+        sync(&mut world, &mut sync_game_map);
 
         let game_map = world.read_resource::<GameMap>();
         assert!(!game_map.tile_is(&pos, TileProperties::BLOCKED));
     }
 
+    #[test]
+    fn opaque_full_block_marks_tile_as_opaque() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::stone())
+            .with(components::Shape::FullBlock)
+            .build();
+        world
+            .create_entity()
+            .with(pos.right())
+            .with(components::material::stone())
+            .with(components::Shape::Floor)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(game_map.tile_is(&pos, TileProperties::OPAQUE));
+        assert!(!game_map.tile_is(&pos.right(), TileProperties::OPAQUE));
+    }
+
+    #[test]
+    fn solid_creatures_occupy_their_tile() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::flesh())
+            .with(components::Shape::Medium)
+            .build();
+        world
+            .create_entity()
+            .with(pos.right())
+            .with(components::material::flesh())
+            .with(components::Shape::Small)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(game_map.tile_is(&pos, TileProperties::OCCUPIED));
+        assert!(!game_map.tile_is(&pos, TileProperties::BLOCKED));
+        assert!(!game_map.tile_is(&pos.right(), TileProperties::OCCUPIED));
+    }
+
+    #[test]
+    fn items_on_the_floor_mark_their_tile() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        let ent = world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::glass())
+            .with(components::Shape::Small)
+            .with(components::Item { effect: None })
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        assert!(world
+            .read_resource::<GameMap>()
+            .tile_is(&pos, TileProperties::ITEMS));
+
+        // Picked up items leave the map
+        world.write_storage::<components::Position>().remove(ent);
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(!game_map.tile_is(&pos, TileProperties::ITEMS));
+        assert!(game_map.get_entities(&pos).is_none());
+    }
+
+    #[test]
+    fn updates_tile_when_something_becomes_an_item() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        let ent = world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::glass())
+            .with(components::Shape::Small)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        assert!(!world
+            .read_resource::<GameMap>()
+            .tile_is(&pos, TileProperties::ITEMS));
+
+        world
+            .write_storage::<components::Item>()
+            .insert(ent, components::Item { effect: None })
+            .unwrap();
+
+        sync(&mut world, &mut sync_game_map);
+
+        assert!(world
+            .read_resource::<GameMap>()
+            .tile_is(&pos, TileProperties::ITEMS));
+
+        world.write_storage::<components::Item>().remove(ent);
+
+        sync(&mut world, &mut sync_game_map);
+
+        assert!(!world
+            .read_resource::<GameMap>()
+            .tile_is(&pos, TileProperties::ITEMS));
+    }
+
+    #[test]
+    fn translucent_things_partly_block_sight() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::smoke())
+            .with(components::Shape::FullBlock)
+            .build();
+        world
+            .create_entity()
+            .with(pos.right())
+            .with(components::material::flesh())
+            .with(components::Shape::Medium)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(!game_map.tile_is(&pos, TileProperties::OPAQUE));
+        assert!((game_map.opacity(&pos) - components::material::smoke().opacity).abs() < 1e-5);
+        assert!(
+            (game_map.opacity(&pos.right()) - components::Shape::Medium.obstruction()).abs() < 1e-5
+        );
+    }
+
     #[test]
     fn full_block_of_stone_marks_tile_as_blocked() {
-        let mut world = build_world();
+        let (mut world, mut sync_game_map) = build_world();
         let pos = components::Position::new(3, -4);
 
         world
@@ -88,9 +364,7 @@ mod tests {
             .with(components::Shape::FullBlock)
             .build();
 
-        let mut sync_game_map = SyncGameMap;
-        sync_game_map.run_now(&world);
-        world.maintain();
+        sync(&mut world, &mut sync_game_map);
 
         let game_map = world.read_resource::<GameMap>();
         assert!(game_map.tile_is(&pos, TileProperties::BLOCKED));
@@ -98,7 +372,7 @@ mod tests {
 
     #[test]
     fn stone_floor_not_blocked() {
-        let mut world = build_world();
+        let (mut world, mut sync_game_map) = build_world();
         let pos = components::Position::new(3, -4);
 
         world
@@ -108,11 +382,115 @@ mod tests {
             .with(components::Shape::Floor)
             .build();
 
-        let mut sync_game_map = SyncGameMap;
-        sync_game_map.run_now(&world);
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(!game_map.tile_is(&pos, TileProperties::BLOCKED));
+    }
+
+    #[test]
+    fn follows_entity_when_it_moves() {
+        let (mut world, mut sync_game_map) = build_world();
+        let start = components::Position::new(3, -4);
+        let target = start.right();
+
+        let ent = world
+            .create_entity()
+            .with(start.clone())
+            .with(components::material::stone())
+            .with(components::Shape::FullBlock)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        world
+            .write_storage::<components::Position>()
+            .get_mut(ent)
+            .unwrap()
+            .set(&target);
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(!game_map.tile_is(&start, TileProperties::BLOCKED));
+        assert!(game_map.get_entities(&start).is_none());
+        assert!(game_map.tile_is(&target, TileProperties::BLOCKED));
+        assert!(game_map.get_entities(&target).unwrap().contains(&ent));
+    }
+
+    #[test]
+    fn removes_deleted_entities() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        let ent = world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::stone())
+            .with(components::Shape::FullBlock)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        world.delete_entity(ent).unwrap();
         world.maintain();
 
+        sync(&mut world, &mut sync_game_map);
+
         let game_map = world.read_resource::<GameMap>();
         assert!(!game_map.tile_is(&pos, TileProperties::BLOCKED));
+        assert!(game_map.get_entities(&pos).is_none());
+    }
+
+    #[test]
+    fn updates_tile_when_material_changes() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        let ent = world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::stone())
+            .with(components::Shape::FullBlock)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        world
+            .write_storage::<components::material::Material>()
+            .insert(ent, components::material::smoke())
+            .unwrap();
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(!game_map.tile_is(&pos, TileProperties::BLOCKED));
+        assert!(game_map.get_entities(&pos).unwrap().contains(&ent));
+    }
+
+    #[test]
+    fn does_not_touch_unchanged_entities() {
+        let (mut world, mut sync_game_map) = build_world();
+        let pos = components::Position::new(3, -4);
+
+        world
+            .create_entity()
+            .with(pos.clone())
+            .with(components::material::stone())
+            .with(components::Shape::FullBlock)
+            .build();
+
+        sync(&mut world, &mut sync_game_map);
+
+        // If the map were rebuilt from scratch this mark would be wiped out
+        world
+            .write_resource::<GameMap>()
+            .mark_tile(&pos.up(), TileProperties::BLOCKED);
+
+        sync(&mut world, &mut sync_game_map);
+
+        let game_map = world.read_resource::<GameMap>();
+        assert!(game_map.tile_is(&pos, TileProperties::BLOCKED));
+        assert!(game_map.tile_is(&pos.up(), TileProperties::BLOCKED));
     }
 }
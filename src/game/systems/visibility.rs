@@ -1,10 +1,31 @@
-use super::super::{components, resources};
-use specs::{Read, ReadStorage, System, WriteStorage};
-
-pub struct Visibility;
-
-const THETA_BUCKET_COUNT: i32 = 1000;
-const THETA_BUCKET_SIZE: f32 = 2. * std::f32::consts::PI / (THETA_BUCKET_COUNT as f32);
+use super::super::{
+    components::{self, equipment},
+    fov,
+    resources::{self, game_map::GameMap, LightMap},
+};
+use specs::{
+    hibitset::{BitSet, BitSetLike},
+    shrev::ReaderId,
+    storage::ComponentEvent,
+    world::Index,
+    Read, ReadStorage, System, SystemData, World, WriteStorage,
+};
+use std::collections::HashMap;
+
+/// Works out what every entity with a viewshed can see.  Viewsheds are only
+/// recomputed when their owner moves, when anything that might block sight
+/// changes within their range, or when they've been marked dirty.  Whatever
+/// is lit in the player's viewshed gets marked Visible and remembered.
+#[derive(Default)]
+pub struct Visibility {
+    position_reader: Option<ReaderId<ComponentEvent>>,
+    material_reader: Option<ReaderId<ComponentEvent>>,
+    shape_reader: Option<ReaderId<ComponentEvent>>,
+
+    // Last known place of everything with a material or shape, so that it can
+    // still be found once it's been deleted along with its position
+    terrain: HashMap<Index, components::Position>,
+}
 
 impl<'a> System<'a> for Visibility {
     type SystemData = (
@@ -13,75 +34,160 @@ impl<'a> System<'a> for Visibility {
         ReadStorage<'a, components::Position>,
         ReadStorage<'a, components::material::Material>,
         ReadStorage<'a, components::Shape>,
+        ReadStorage<'a, equipment::Equipment>,
+        ReadStorage<'a, equipment::Equippable>,
+        Read<'a, GameMap>,
+        Option<Read<'a, LightMap>>,
+        WriteStorage<'a, components::Viewshed>,
         WriteStorage<'a, components::Visible>,
+        WriteStorage<'a, components::Explored>,
     );
 
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+
+        self.position_reader =
+            Some(WriteStorage::<components::Position>::fetch(world).register_reader());
+        self.material_reader =
+            Some(WriteStorage::<components::material::Material>::fetch(world).register_reader());
+        self.shape_reader = Some(WriteStorage::<components::Shape>::fetch(world).register_reader());
+    }
+
     fn run(
         &mut self,
-        (entities, player, positions, materials, shapes, mut visibles): Self::SystemData,
+        (
+            entities,
+            player,
+            positions,
+            materials,
+            shapes,
+            equipment,
+            equippables,
+            game_map,
+            light_map,
+            mut viewsheds,
+            mut visibles,
+            mut explored,
+        ): Self::SystemData,
     ) {
         use specs::Join;
 
-        if let Some(player) = player {
-            visibles.clear();
+        let mut moved = BitSet::new();
+
+        for event in positions.channel().read(
+            self.position_reader
+                .as_mut()
+                .expect("Visibility was not set up"),
+        ) {
+            if let ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) = event {
+                moved.add(*id);
+            }
+        }
 
-            let ent_player = player.ent;
-            let pos_player = positions.get(ent_player).unwrap();
-            let mut max_vision_blocked = std::collections::HashMap::new();
+        let terrain_events: Vec<ComponentEvent> = materials
+            .channel()
+            .read(
+                self.material_reader
+                    .as_mut()
+                    .expect("Visibility was not set up"),
+            )
+            .chain(
+                shapes.channel().read(
+                    self.shape_reader
+                        .as_mut()
+                        .expect("Visibility was not set up"),
+                ),
+            )
+            .cloned()
+            .collect();
+
+        // Walls going up or coming down could change what anyone nearby can
+        // see, and so could anything partly see through moving around
+        let mut terrain_changed = Vec::new();
+
+        for id in (&moved).iter() {
+            if let (Some(known), Some(pos)) = (
+                self.terrain.get_mut(&id),
+                positions.get(entities.entity(id)),
+            ) {
+                terrain_changed.push(known.clone());
+                terrain_changed.push(pos.clone());
+                *known = pos.clone();
+            }
+        }
 
-            let get_bucket = |theta: f32| -> i32 { (theta / THETA_BUCKET_SIZE) as i32 };
+        for event in terrain_events {
+            let (id, removed) = match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => (id, false),
+                ComponentEvent::Removed(id) => (id, true),
+            };
 
-            // This isn't great but it's simple and works for now
-            for (pos, material, shape) in (&positions, &materials, &shapes).join() {
-                if components::Shape::FullBlock != *shape || !material.opaque {
-                    continue;
+            match positions.get(entities.entity(id)) {
+                Some(pos) => {
+                    self.terrain.insert(id, pos.clone());
+                    terrain_changed.push(pos.clone());
                 }
+                None if removed => terrain_changed.extend(self.terrain.remove(&id)),
+                None => (),
+            }
+        }
 
-                let thetas = pos_player.visible_corner_thetas(pos);
-                let distance = pos_player.distance_squared(pos);
+        let ent_player = player.map(|player| player.ent);
 
-                let (mut min_theta, mut max_theta) = if thetas.0 < thetas.1 {
-                    (thetas.0, thetas.1)
-                } else {
-                    (thetas.1, thetas.0)
-                };
+        for (entity, pos, viewshed) in (&entities, &positions, &mut viewsheds).join() {
+            // Equipment can help or hinder seeing far, but never blinds completely
+            let vision = equipment::bonuses_of(entity, &equipment, &equippables, &materials).vision;
+            let radius = (viewshed.radius + vision).max(0);
 
-                if (max_theta - min_theta).abs() > std::f32::consts::PI {
-                    let new_min_theta = max_theta;
-                    let new_max_theta = min_theta + std::f32::consts::PI * 2.;
+            let terrain_in_range = terrain_changed.iter().any(|changed| {
+                viewshed.can_see(changed)
+                    || (changed.x - pos.x).abs() <= radius && (changed.y - pos.y).abs() <= radius
+            });
 
-                    min_theta = new_min_theta;
-                    max_theta = new_max_theta;
-                }
+            if !viewshed.dirty && !terrain_in_range && !moved.contains(entity.id()) {
+                continue;
+            }
 
-                let min_bucket = get_bucket(min_theta);
-                let max_bucket = get_bucket(max_theta);
+            viewshed.visible = fov::field_of_view(&game_map, pos, radius);
+            viewshed.dirty = false;
+        }
 
-                for bucket in min_bucket..=max_bucket {
-                    let existing_max = max_vision_blocked
-                        .entry(bucket % THETA_BUCKET_COUNT)
-                        .or_insert(100000_f32);
+        let ent_player = match ent_player {
+            Some(ent) => ent,
+            None => return,
+        };
 
-                    if *existing_max > distance {
-                        *existing_max = distance;
-                    }
-                }
-            }
+        visibles.clear();
 
-            for (entity, pos, material) in (&entities, &positions, &materials).join() {
-                if !material.visible {
-                    continue;
-                }
+        let viewshed = match viewsheds.get(ent_player) {
+            Some(viewshed) => viewshed,
+            None => return,
+        };
+
+        let mut explored = explored.get_mut(ent_player);
 
-                let theta = pos_player.theta(&pos);
-                let distance = pos_player.distance_squared_to_nearest_point(&pos);
+        // Creatures and lights move around without the viewshed changing, so
+        // this part happens every time.  Without a light map everything is lit.
+        for pos in &viewshed.visible {
+            let entities = match game_map.get_entities(pos) {
+                Some(entities) => entities,
+                None => continue,
+            };
+
+            if light_map.as_ref().is_some_and(|lights| !lights.is_lit(pos)) {
+                continue;
+            }
+
+            if let Some(explored) = explored.as_mut() {
+                explored.tiles.insert(pos.clone());
+            }
 
-                if let Some(max_distance) = max_vision_blocked.get(&get_bucket(theta)) {
-                    if distance <= *max_distance {
-                        visibles.insert(entity, components::Visible).unwrap();
-                    }
-                } else {
-                    visibles.insert(entity, components::Visible).unwrap();
+            for entity in entities {
+                if materials
+                    .get(*entity)
+                    .is_some_and(|material| material.visible)
+                {
+                    visibles.insert(*entity, components::Visible).unwrap();
                 }
             }
         }
@@ -91,10 +197,11 @@ impl<'a> System<'a> for Visibility {
 #[cfg(test)]
 mod tests {
     use super::super::super::entities;
+    use super::super::sync_game_map::SyncGameMap;
     use super::*;
-    use specs::{Builder, RunNow, World, WorldExt};
+    use specs::{Builder, Join, RunNow, World, WorldExt};
 
-    fn build_world() -> specs::World {
+    fn build_world() -> (specs::World, SyncGameMap, Visibility) {
         let mut world = World::new();
 
         world.register::<components::Position>();
@@ -103,8 +210,37 @@ mod tests {
         world.register::<components::Shape>();
         world.register::<components::Visible>();
         world.register::<components::Sprite>();
+        world.register::<components::Energy>();
+        world.register::<components::Explored>();
+        world.register::<components::Viewshed>();
+        world.register::<components::LightSource>();
+        world.register::<components::Faction>();
+        world.register::<components::Health>();
+        world.register::<components::Attack>();
+        world.register::<components::Defense>();
+        world.register::<components::Inventory>();
+        world.register::<components::LeavesCorpse>();
+        world.insert(GameMap::new());
+
+        let mut sync_game_map = SyncGameMap::default();
+        System::setup(&mut sync_game_map, &mut world);
+
+        let mut visibility = Visibility::default();
+        System::setup(&mut visibility, &mut world);
+
+        (world, sync_game_map, visibility)
+    }
 
-        world
+    fn look(
+        world: &mut specs::World,
+        sync_game_map: &mut SyncGameMap,
+        visibility: &mut Visibility,
+    ) {
+        sync_game_map.run_now(world);
+        world.maintain();
+
+        visibility.run_now(world);
+        world.maintain();
     }
 
     fn add_stone_wall(world: &mut specs::World, pos: components::Position) -> specs::Entity {
@@ -130,13 +266,11 @@ mod tests {
 
     #[test]
     fn marks_player_visible() {
-        let mut world = build_world();
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
 
         let ent_player = entities::player::create_in(&mut world, components::Position::new(0, 0));
 
-        let mut visibility = Visibility;
-        visibility.run_now(&world);
-        world.maintain();
+        look(&mut world, &mut sync_game_map, &mut visibility);
 
         let read_visible = world.read_storage::<components::Visible>();
         let player_visible = read_visible.get(ent_player);
@@ -147,18 +281,47 @@ mod tests {
         };
     }
 
+    #[test]
+    fn equipment_changes_how_far_you_see() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+
+        let spyglass = world
+            .create_entity()
+            .with(equipment::Equippable {
+                slot: equipment::Slot::Light,
+                attack: 0,
+                vision: 3,
+            })
+            .build();
+        let viewer = world
+            .create_entity()
+            .with(components::Position::new(0, 0))
+            .with(components::Viewshed::new(2))
+            .with(equipment::Equipment {
+                light: Some(spyglass),
+                ..Default::default()
+            })
+            .build();
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        let viewsheds = world.read_storage::<components::Viewshed>();
+        let viewshed = viewsheds.get(viewer).unwrap();
+
+        assert!(viewshed.can_see(&components::Position::new(5, 0)));
+        assert!(!viewshed.can_see(&components::Position::new(6, 0)));
+    }
+
     #[test]
     fn marks_opaque_entities_visible() {
-        let mut world = build_world();
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
         let pos_player = components::Position::new(3, -4);
         let pos_other = pos_player.right();
 
         entities::player::create_in(&mut world, pos_player.clone());
         let ent_other = add_generic_medium_creature(&mut world, pos_other.clone());
 
-        let mut visibility = Visibility;
-        visibility.run_now(&world);
-        world.maintain();
+        look(&mut world, &mut sync_game_map, &mut visibility);
 
         let read_visible = world.read_storage::<components::Visible>();
         let other_visible = read_visible.get(ent_other);
@@ -171,7 +334,7 @@ mod tests {
 
     #[test]
     fn does_not_mark_creature_visible_if_behind_wall() {
-        let mut world = build_world();
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
         let pos_player = components::Position::new(3, -4);
         let pos_wall = pos_player.right();
         let pos_creature = pos_wall.right();
@@ -180,9 +343,7 @@ mod tests {
         let ent_wall = add_stone_wall(&mut world, pos_wall);
         let ent_creature = add_generic_medium_creature(&mut world, pos_creature);
 
-        let mut visibility = Visibility;
-        visibility.run_now(&world);
-        world.maintain();
+        look(&mut world, &mut sync_game_map, &mut visibility);
 
         let read_visible = world.read_storage::<components::Visible>();
         let wall_visible = read_visible.get(ent_wall);
@@ -211,7 +372,7 @@ mod tests {
 
         None of the "C"s should be visible to the player.
         */
-        let mut world = build_world();
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
         let pos_player = components::Position::new(3, -4);
         let pos_center_wall = pos_player.right();
 
@@ -239,22 +400,15 @@ mod tests {
             }
         }
 
-        let mut visibility = Visibility;
-        visibility.run_now(&world);
-        world.maintain();
+        look(&mut world, &mut sync_game_map, &mut visibility);
 
         let read_visible = world.read_storage::<components::Visible>();
 
-        /*
-        // TODO: Make this true
         for ent_wall in ent_walls {
-            let wall_visible = read_visible.get(ent_wall);
-            match wall_visible {
-                None => panic!("Wall not visible but should be"),
-                Some(_) => (),
-            };
+            if read_visible.get(ent_wall).is_none() {
+                panic!("Wall not visible but should be");
+            }
         }
-        */
 
         for ent_creature in ent_creatures {
             let creature_visible = read_visible.get(ent_creature);
@@ -264,4 +418,245 @@ mod tests {
             };
         }
     }
+
+    #[test]
+    fn remembers_tiles_after_they_go_out_of_view() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+        let pos_player = components::Position::new(3, -4);
+        let pos_wall = pos_player.right();
+        let pos_behind = pos_wall.right();
+
+        let ent_player = entities::player::create_in(&mut world, pos_player.clone());
+        let ent_creature = add_generic_medium_creature(&mut world, pos_behind.clone());
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        add_stone_wall(&mut world, pos_wall.clone());
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        let read_explored = world.read_storage::<components::Explored>();
+        let explored = read_explored.get(ent_player).unwrap();
+
+        assert!(explored.tiles.contains(&pos_player));
+        assert!(explored.tiles.contains(&pos_wall));
+        assert!(explored.tiles.contains(&pos_behind));
+        assert!(world
+            .read_storage::<components::Visible>()
+            .get(ent_creature)
+            .is_none());
+    }
+
+    #[test]
+    fn computes_viewsheds_for_npcs() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+        let pos_npc = components::Position::new(3, -4);
+        let pos_wall = pos_npc.right();
+
+        add_stone_wall(&mut world, pos_wall.clone());
+
+        let ent_npc = world
+            .create_entity()
+            .with(pos_npc.clone())
+            .with(components::material::flesh())
+            .with(components::Shape::Medium)
+            .with(components::Viewshed::new(5))
+            .build();
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        let read_viewsheds = world.read_storage::<components::Viewshed>();
+        let viewshed = read_viewsheds.get(ent_npc).unwrap();
+
+        assert!(viewshed.can_see(&pos_npc));
+        assert!(viewshed.can_see(&pos_wall));
+        assert!(viewshed.can_see(&pos_npc.left().left()));
+        assert!(!viewshed.can_see(&pos_wall.right()));
+        assert!(!viewshed.dirty);
+
+        // Only the player's view decides what gets drawn
+        assert!(world
+            .read_storage::<components::Visible>()
+            .get(ent_npc)
+            .is_none());
+    }
+
+    #[test]
+    fn only_recomputes_viewsheds_when_needed() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+        let start = components::Position::new(0, 0);
+
+        let ent_npc = world
+            .create_entity()
+            .with(start.clone())
+            .with(components::Viewshed::new(3))
+            .build();
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        world
+            .write_storage::<components::Viewshed>()
+            .get_mut(ent_npc)
+            .unwrap()
+            .visible
+            .clear();
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        assert!(world
+            .read_storage::<components::Viewshed>()
+            .get(ent_npc)
+            .unwrap()
+            .visible
+            .is_empty());
+
+        world
+            .write_storage::<components::Position>()
+            .get_mut(ent_npc)
+            .unwrap()
+            .set(&start.right());
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        assert!(world
+            .read_storage::<components::Viewshed>()
+            .get(ent_npc)
+            .unwrap()
+            .can_see(&start.right().right()));
+    }
+
+    #[test]
+    fn only_viewers_in_range_notice_terrain_changes() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+        let near = components::Position::new(0, 0);
+        let far = components::Position::new(100, 0);
+
+        let ent_near = world
+            .create_entity()
+            .with(near.clone())
+            .with(components::Viewshed::new(3))
+            .build();
+        let ent_far = world
+            .create_entity()
+            .with(far.clone())
+            .with(components::Viewshed::new(3))
+            .build();
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        let forget = |world: &mut specs::World| {
+            for viewshed in (&mut world.write_storage::<components::Viewshed>()).join() {
+                viewshed.visible.clear();
+            }
+        };
+        let sees_anything = |world: &specs::World, entity| {
+            !world
+                .read_storage::<components::Viewshed>()
+                .get(entity)
+                .unwrap()
+                .visible
+                .is_empty()
+        };
+
+        // Only the nearby viewer recomputes, so only it sees anything again
+        forget(&mut world);
+        let wall = add_stone_wall(&mut world, near.right().right());
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        assert!(sees_anything(&world, ent_near));
+        assert!(!sees_anything(&world, ent_far));
+
+        // Walls are gone along with their position once deleted
+        forget(&mut world);
+        world.delete_entity(wall).unwrap();
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        assert!(sees_anything(&world, ent_near));
+        assert!(!sees_anything(&world, ent_far));
+    }
+
+    #[test]
+    fn notices_creatures_stepping_into_view() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+        let origin = components::Position::new(0, 0);
+        let beyond = components::Position::new(7, 0);
+        let aside = components::Position::new(3, 1);
+
+        let ent_viewer = world
+            .create_entity()
+            .with(origin.clone())
+            .with(components::Viewshed::new(8))
+            .build();
+
+        // A crowd thin enough to see through, until one more joins it
+        for x in 1..6 {
+            add_generic_medium_creature(&mut world, components::Position::new(x, 0));
+        }
+
+        let ent_creature = add_generic_medium_creature(&mut world, aside.clone());
+
+        let can_see_beyond = |world: &specs::World| {
+            world
+                .read_storage::<components::Viewshed>()
+                .get(ent_viewer)
+                .unwrap()
+                .can_see(&beyond)
+        };
+        let step = |world: &mut specs::World, pos: &components::Position| {
+            world
+                .write_storage::<components::Position>()
+                .get_mut(ent_creature)
+                .unwrap()
+                .set(pos);
+        };
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+        assert!(can_see_beyond(&world));
+
+        step(&mut world, &beyond.left());
+        look(&mut world, &mut sync_game_map, &mut visibility);
+        assert!(!can_see_beyond(&world));
+
+        step(&mut world, &aside);
+        look(&mut world, &mut sync_game_map, &mut visibility);
+        assert!(can_see_beyond(&world));
+    }
+
+    #[test]
+    fn does_not_mark_things_in_the_dark_visible() {
+        let (mut world, mut sync_game_map, mut visibility) = build_world();
+        let pos_player = components::Position::new(0, 0);
+        let mut light_map = LightMap::default();
+
+        light_map
+            .tiles
+            .insert(pos_player.right(), resources::Light::default());
+        light_map.tiles.insert(
+            pos_player.left(),
+            resources::Light {
+                r: 1.,
+                g: 1.,
+                b: 1.,
+            },
+        );
+
+        world.insert(light_map);
+
+        let ent_player = entities::player::create_in(&mut world, pos_player.clone());
+        let ent_dark = add_generic_medium_creature(&mut world, pos_player.right());
+        let ent_lit = add_generic_medium_creature(&mut world, pos_player.left());
+
+        look(&mut world, &mut sync_game_map, &mut visibility);
+
+        let read_visible = world.read_storage::<components::Visible>();
+
+        assert!(read_visible.get(ent_dark).is_none());
+        assert!(read_visible.get(ent_lit).is_some());
+
+        let read_explored = world.read_storage::<components::Explored>();
+        let explored = read_explored.get(ent_player).unwrap();
+
+        assert!(explored.tiles.contains(&pos_player.left()));
+        assert!(!explored.tiles.contains(&pos_player.right()));
+    }
 }
@@ -0,0 +1,15 @@
+// Lints newer toolchains raise against the original code, which is kept as it
+// was written rather than rewritten to suit them
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::needless_borrow,
+    clippy::needless_return,
+    clippy::single_match,
+    clippy::unnecessary_cast,
+    clippy::while_let_loop
+)]
+
+#[macro_use]
+extern crate bitflags;
+
+pub mod game;
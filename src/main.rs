@@ -1,12 +1,12 @@
-#[macro_use]
-extern crate bitflags;
-
-use std::io::{stdout, Write};
+use std::{
+    io::{stdout, Write},
+    path::PathBuf,
+};
 
 use termion::{async_stdin, clear, cursor, input::TermRead, raw::IntoRawMode, style};
 
-mod game;
-
+use adventure::game;
+use game::level::LevelConfig;
 use game::systems::render::{Color, Renderer};
 
 struct Terminal<'a, W: Write> {
@@ -38,7 +38,108 @@ impl<'a, W: Write> Renderer for Terminal<'a, W> {
     }
 }
 
+/// Where new games are saved, or numbered copies of it like `adventure-2.save`
+/// when that's already taken
+const DEFAULT_SAVE: &str = "adventure.save";
+
+enum Launch {
+    /// The seed places monsters and items, and generates the level too unless
+    /// it comes from a file
+    New {
+        config: LevelConfig,
+        seed: u64,
+    },
+    Load(PathBuf),
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Launch, String> {
+    let mut cave = false;
+    let mut seed = None;
+    let mut path = None;
+    let mut load = None;
+    let mut args = args;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cave" => cave = true,
+            "--load" => load = Some(PathBuf::from(args.next().ok_or("--load needs a file")?)),
+            "--seed" => {
+                let value = args.next().ok_or("--seed needs a value")?;
+                let value = value
+                    .parse()
+                    .map_err(|_| format!("Seed must be a number, got '{}'", value))?;
+
+                seed = Some(value);
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'", arg)),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    if let Some(load) = load {
+        if cave || seed.is_some() || path.is_some() {
+            return Err(String::from(
+                "Saved games can't be combined with --cave, --seed or a level file",
+            ));
+        }
+
+        return Ok(Launch::Load(load));
+    }
+
+    let seed = seed.unwrap_or_else(rand::random);
+
+    let config = match path {
+        Some(path) => {
+            if cave {
+                return Err(String::from("Level files can't be combined with --cave"));
+            }
+
+            LevelConfig::File(path)
+        }
+        None if cave => LevelConfig::Cave { seed },
+        None => LevelConfig::Dungeon { seed },
+    };
+
+    Ok(Launch::New { config, seed })
+}
+
 fn main() {
+    let launch = match parse_args(std::env::args().skip(1)) {
+        Ok(launch) => launch,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "Usage: adventure [--seed <number>] [--cave | <level file>] | --load <save file>"
+            );
+            std::process::exit(1);
+        }
+    };
+
+    // Everything that can fail to load does so before the terminal goes raw
+    let (start, save_path) = match &launch {
+        Launch::New { config, seed } => {
+            let level = match config.build() {
+                Ok(level) => level,
+                Err(e) => {
+                    eprintln!("Failed to load level: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            (
+                game::Start::New { level, seed: *seed },
+                PathBuf::from(DEFAULT_SAVE),
+            )
+        }
+        Launch::Load(path) => match game::save::read(path) {
+            Ok(save) => (game::Start::Continue(save), path.clone()),
+            Err(e) => {
+                eprintln!("Failed to load saved game: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+
     let stdout = stdout();
     let stdin = async_stdin();
     let mut stdout = stdout.lock().into_raw_mode().unwrap();
@@ -52,7 +153,7 @@ fn main() {
 
     let input = game::input::Input::new(stdin.keys());
 
-    game::run(terminal, input);
+    let exit = game::run(terminal, input, start, &save_path);
 
     write!(
         stdout,
@@ -64,4 +165,29 @@ fn main() {
     )
     .unwrap();
     stdout.flush().unwrap();
+
+    match launch {
+        Launch::New {
+            config: LevelConfig::Dungeon { .. },
+            seed,
+        } => println!("Dungeon seed: {}", seed),
+        Launch::New {
+            config: LevelConfig::Cave { .. },
+            seed,
+        } => println!("Cave seed: {}", seed),
+        Launch::New {
+            config: LevelConfig::File(_),
+            seed,
+        } => println!("Seed: {}", seed),
+        Launch::Load(_) => (),
+    }
+
+    match exit {
+        Ok(game::Exit::Saved(path)) => println!("Game saved to {}", path.display()),
+        Ok(game::Exit::Died) => (),
+        Err(e) => {
+            eprintln!("Failed to save game: {}", e);
+            std::process::exit(1);
+        }
+    }
 }